@@ -0,0 +1,25 @@
+-- Live programmes ("Programa en vivo") and the tracks/albums played during them
+
+CREATE TABLE IF NOT EXISTS live_sets (
+    id BIGSERIAL PRIMARY KEY,
+    name VARCHAR NOT NULL,
+    aired_at VARCHAR,
+    notes TEXT,
+    created_at VARCHAR NOT NULL
+);
+
+-- offset_seconds is measured from the start of the programme
+CREATE TABLE IF NOT EXISTS live_set_entries (
+    id BIGSERIAL PRIMARY KEY,
+    live_set_id BIGINT NOT NULL,
+    track_id BIGINT,
+    album_id BIGINT,
+    offset_seconds BIGINT NOT NULL DEFAULT 0,
+    note TEXT,
+    created_at VARCHAR NOT NULL,
+    FOREIGN KEY (live_set_id) REFERENCES live_sets(id) ON DELETE CASCADE,
+    FOREIGN KEY (track_id) REFERENCES tracks(id),
+    FOREIGN KEY (album_id) REFERENCES albums(id)
+);
+
+CREATE INDEX IF NOT EXISTS live_set_entries_live_set_id_idx ON live_set_entries (live_set_id, offset_seconds);
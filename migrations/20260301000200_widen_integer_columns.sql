@@ -0,0 +1,3 @@
+-- Models read these columns as i64, which requires BIGINT in PostgreSQL
+ALTER TABLE albums ALTER COLUMN release_year TYPE BIGINT;
+ALTER TABLE tracks ALTER COLUMN duration_seconds TYPE BIGINT;
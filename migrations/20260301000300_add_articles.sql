@@ -0,0 +1,17 @@
+-- Editorial articles. The Markdown files in articles_dir are the source of truth;
+-- rows are kept in sync with them by articles::sync_articles.
+
+CREATE TABLE IF NOT EXISTS articles (
+    id BIGSERIAL PRIMARY KEY,
+    slug VARCHAR NOT NULL UNIQUE,
+    filename VARCHAR NOT NULL,
+    title VARCHAR NOT NULL,
+    author VARCHAR,
+    status VARCHAR NOT NULL DEFAULT 'draft' CHECK (status IN ('draft', 'published', 'archived')),
+    published_at VARCHAR,
+    entrega_id BIGINT,
+    created_at VARCHAR NOT NULL,
+    FOREIGN KEY (entrega_id) REFERENCES entregas(id)
+);
+
+CREATE INDEX IF NOT EXISTS articles_entrega_id_idx ON articles (entrega_id);
@@ -0,0 +1,36 @@
+-- Full-text search over the catalogue and the editorial content.
+-- Every searchable table gets a generated tsvector combining the Spanish
+-- configuration (stemming) with the simple one (exact names, English titles).
+
+ALTER TABLE articles ADD COLUMN IF NOT EXISTS body TEXT NOT NULL DEFAULT '';
+
+ALTER TABLE tracks ADD COLUMN IF NOT EXISTS search_vector tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector('spanish', coalesce(title, '')), 'A') ||
+    setweight(to_tsvector('simple', coalesce(title, '')), 'A') ||
+    setweight(to_tsvector('simple', coalesce(artist_name, '')), 'B')
+) STORED;
+
+ALTER TABLE albums ADD COLUMN IF NOT EXISTS search_vector tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector('spanish', coalesce(title, '')), 'A') ||
+    setweight(to_tsvector('simple', coalesce(title, '')), 'A') ||
+    setweight(to_tsvector('simple', coalesce(label, '')), 'B') ||
+    setweight(to_tsvector('spanish', coalesce(genre, '') || ' ' || coalesce(style, '')), 'C') ||
+    setweight(to_tsvector('simple', coalesce(genre, '') || ' ' || coalesce(style, '')), 'C')
+) STORED;
+
+ALTER TABLE artists ADD COLUMN IF NOT EXISTS search_vector tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector('simple', coalesce(name, '')), 'A')
+) STORED;
+
+ALTER TABLE articles ADD COLUMN IF NOT EXISTS search_vector tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector('spanish', coalesce(title, '')), 'A') ||
+    setweight(to_tsvector('simple', coalesce(title, '')), 'A') ||
+    setweight(to_tsvector('simple', coalesce(author, '')), 'B') ||
+    setweight(to_tsvector('spanish', body), 'D') ||
+    setweight(to_tsvector('simple', body), 'D')
+) STORED;
+
+CREATE INDEX IF NOT EXISTS tracks_search_idx ON tracks USING GIN (search_vector);
+CREATE INDEX IF NOT EXISTS albums_search_idx ON albums USING GIN (search_vector);
+CREATE INDEX IF NOT EXISTS artists_search_idx ON artists USING GIN (search_vector);
+CREATE INDEX IF NOT EXISTS articles_search_idx ON articles USING GIN (search_vector);
@@ -0,0 +1,26 @@
+-- Credit tracks to artist rows instead of the free-text tracks.artist_name.
+-- artist_name is kept as the display credit; the main credit is mirrored here.
+
+CREATE TABLE IF NOT EXISTS track_artists (
+    track_id BIGINT NOT NULL,
+    artist_id BIGINT NOT NULL,
+    role VARCHAR NOT NULL DEFAULT 'main' CHECK (role IN ('main', 'featuring', 'remixer', 'producer')),
+    PRIMARY KEY (track_id, artist_id, role),
+    FOREIGN KEY (track_id) REFERENCES tracks(id) ON DELETE CASCADE,
+    FOREIGN KEY (artist_id) REFERENCES artists(id)
+);
+
+CREATE INDEX IF NOT EXISTS track_artists_artist_id_idx ON track_artists (artist_id);
+
+-- Backfill one artist per distinct artist_name
+INSERT INTO artists (name, created_at)
+SELECT DISTINCT t.artist_name, to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS"Z"')
+FROM tracks t
+WHERE t.artist_name <> 'Unknown'
+  AND NOT EXISTS (SELECT 1 FROM artists a WHERE a.name = t.artist_name);
+
+INSERT INTO track_artists (track_id, artist_id, role)
+SELECT t.id, (SELECT min(a.id) FROM artists a WHERE a.name = t.artist_name), 'main'
+FROM tracks t
+WHERE t.artist_name <> 'Unknown'
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,16 @@
+-- Audit trail of duplicate artists merged into a surviving artist.
+-- No foreign keys: the source artist no longer exists and the record must
+-- outlive later changes to the target.
+
+CREATE TABLE IF NOT EXISTS artist_merges (
+    id BIGSERIAL PRIMARY KEY,
+    target_artist_id BIGINT NOT NULL,
+    source_artist_id BIGINT NOT NULL,
+    source_name VARCHAR NOT NULL,
+    source_country VARCHAR,
+    track_credits_moved BIGINT NOT NULL,
+    albums_moved BIGINT NOT NULL,
+    merged_at VARCHAR NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS artist_merges_target_artist_id_idx ON artist_merges (target_artist_id);
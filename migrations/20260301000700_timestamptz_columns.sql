@@ -0,0 +1,40 @@
+-- created_at was a client-supplied VARCHAR. Convert it to TIMESTAMPTZ with a
+-- server-side default and add updated_at, maintained by the update handlers.
+-- A value that does not parse as a timestamp aborts the migration, naming the
+-- row, so it can be corrected by hand instead of losing the original date.
+
+CREATE FUNCTION pg_temp.parse_timestamptz(value TEXT, source TEXT) RETURNS TIMESTAMPTZ AS $$
+BEGIN
+    RETURN value::TIMESTAMPTZ;
+EXCEPTION WHEN invalid_datetime_format OR datetime_field_overflow THEN
+    RAISE EXCEPTION '% is %, which is not a timestamp; correct it and run the migrations again',
+        source, quote_literal(value);
+END;
+$$ LANGUAGE plpgsql;
+
+DO $$
+DECLARE
+    table_name TEXT;
+BEGIN
+    FOREACH table_name IN ARRAY ARRAY['artists', 'albums', 'tracks', 'batches', 'entregas', 'live_sets', 'live_set_entries', 'articles'] LOOP
+        EXECUTE format(
+            'ALTER TABLE %I
+                 ALTER COLUMN created_at TYPE TIMESTAMPTZ USING pg_temp.parse_timestamptz(created_at, %L || '' '' || id || '' created_at''),
+                 ALTER COLUMN created_at SET DEFAULT now(),
+                 ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ',
+            table_name, table_name
+        );
+        EXECUTE format('UPDATE %I SET updated_at = created_at', table_name);
+        EXECUTE format(
+            'ALTER TABLE %I
+                 ALTER COLUMN updated_at SET DEFAULT now(),
+                 ALTER COLUMN updated_at SET NOT NULL',
+            table_name
+        );
+        EXECUTE format('CREATE INDEX IF NOT EXISTS %I ON %I (created_at)', table_name || '_created_at_idx', table_name);
+    END LOOP;
+END $$;
+
+ALTER TABLE artist_merges
+    ALTER COLUMN merged_at TYPE TIMESTAMPTZ USING pg_temp.parse_timestamptz(merged_at, 'artist_merges ' || id || ' merged_at'),
+    ALTER COLUMN merged_at SET DEFAULT now();
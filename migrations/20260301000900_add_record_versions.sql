@@ -0,0 +1,25 @@
+-- Optimistic concurrency: every editable record carries a version, bumped by
+-- a trigger on each UPDATE. It is sent as the ETag and must come back in
+-- If-Match on PUT, PATCH and DELETE.
+
+CREATE OR REPLACE FUNCTION bump_version() RETURNS TRIGGER AS $$
+BEGIN
+    NEW.version := OLD.version + 1;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DO $$
+DECLARE
+    table_name TEXT;
+BEGIN
+    FOREACH table_name IN ARRAY ARRAY['artists', 'albums', 'tracks', 'batches', 'entregas', 'live_sets', 'live_set_entries'] LOOP
+        EXECUTE format('ALTER TABLE %I ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1', table_name);
+        EXECUTE format('DROP TRIGGER IF EXISTS %I ON %I', table_name || '_bump_version', table_name);
+        EXECUTE format(
+            'CREATE TRIGGER %I BEFORE UPDATE ON %I FOR EACH ROW EXECUTE FUNCTION bump_version()',
+            table_name || '_bump_version',
+            table_name
+        );
+    END LOOP;
+END $$;
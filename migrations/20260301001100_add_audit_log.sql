@@ -0,0 +1,85 @@
+-- Every insert, update and delete on the catalogue tables is recorded with the
+-- row before and after the change. The actor comes from the transaction-local
+-- setting audit.actor (see audit::begin); writes without it are logged as system.
+
+CREATE TABLE IF NOT EXISTS audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    occurred_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    actor VARCHAR NOT NULL,
+    action VARCHAR NOT NULL,
+    table_name VARCHAR NOT NULL,
+    row_id BIGINT,
+    before JSONB,
+    after JSONB,
+    CONSTRAINT audit_log_action_check CHECK (action IN ('create', 'update', 'trash', 'restore', 'delete'))
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_record_idx ON audit_log (table_name, row_id, id);
+CREATE INDEX IF NOT EXISTS audit_log_actor_idx ON audit_log (actor, id);
+
+-- TG_ARGV[0] names the column used as row_id
+CREATE OR REPLACE FUNCTION audit_row_change() RETURNS TRIGGER AS $$
+DECLARE
+    old_row JSONB;
+    new_row JSONB;
+    change TEXT;
+BEGIN
+    -- Generated search vectors are derived data and would only add noise
+    IF TG_OP <> 'INSERT' THEN
+        old_row := to_jsonb(OLD) - 'search_vector';
+    END IF;
+    IF TG_OP <> 'DELETE' THEN
+        new_row := to_jsonb(NEW) - 'search_vector';
+    END IF;
+
+    IF TG_OP = 'INSERT' THEN
+        change := 'create';
+    ELSIF TG_OP = 'DELETE' THEN
+        change := 'delete';
+    ELSIF old_row - 'updated_at' - 'version' = new_row - 'updated_at' - 'version' THEN
+        RETURN NULL;
+    ELSIF old_row ->> 'deleted_at' IS NULL AND new_row ->> 'deleted_at' IS NOT NULL THEN
+        change := 'trash';
+    ELSIF old_row ->> 'deleted_at' IS NOT NULL AND new_row ->> 'deleted_at' IS NULL THEN
+        change := 'restore';
+    ELSE
+        change := 'update';
+    END IF;
+
+    INSERT INTO audit_log (actor, action, table_name, row_id, before, after)
+    VALUES (
+        COALESCE(NULLIF(current_setting('audit.actor', true), ''), 'system'),
+        change,
+        TG_TABLE_NAME,
+        (COALESCE(new_row, old_row) ->> TG_ARGV[0])::BIGINT,
+        old_row,
+        new_row
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DO $$
+DECLARE
+    audited TEXT[];
+BEGIN
+    FOREACH audited SLICE 1 IN ARRAY ARRAY[
+        ['artists', 'id'],
+        ['albums', 'id'],
+        ['tracks', 'id'],
+        ['batches', 'id'],
+        ['entregas', 'id'],
+        ['live_sets', 'id'],
+        ['live_set_entries', 'id'],
+        ['articles', 'id'],
+        ['track_artists', 'track_id']
+    ] LOOP
+        EXECUTE format('DROP TRIGGER IF EXISTS %I ON %I', audited[1] || '_audit', audited[1]);
+        EXECUTE format(
+            'CREATE TRIGGER %I AFTER INSERT OR UPDATE OR DELETE ON %I FOR EACH ROW EXECUTE FUNCTION audit_row_change(%L)',
+            audited[1] || '_audit',
+            audited[1],
+            audited[2]
+        );
+    END LOOP;
+END $$;
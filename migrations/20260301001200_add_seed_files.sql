@@ -0,0 +1,10 @@
+-- Seed files loaded at startup or by `seed`, like _sqlx_migrations for data.
+-- A new file is imported skipping records that already exist. A file is
+-- applied again only when its checksum changes, with existing=update, so the
+-- records it names are updated with the fields it gives and new ones added.
+
+CREATE TABLE IF NOT EXISTS seed_files (
+    name VARCHAR PRIMARY KEY,
+    checksum VARCHAR NOT NULL, -- SHA-256 of the file, hex encoded
+    applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
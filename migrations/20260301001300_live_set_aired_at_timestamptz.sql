@@ -0,0 +1,17 @@
+-- aired_at was free text, so `sort=aired_at` ordered it as a string. Convert it
+-- to TIMESTAMPTZ; a value that does not parse aborts the migration, naming the
+-- live set, so it can be corrected by hand.
+
+-- OR REPLACE: the migrations run in one session, where 20260301000700 defined it already
+CREATE OR REPLACE FUNCTION pg_temp.parse_timestamptz(value TEXT, source TEXT) RETURNS TIMESTAMPTZ AS $$
+BEGIN
+    RETURN value::TIMESTAMPTZ;
+EXCEPTION WHEN invalid_datetime_format OR datetime_field_overflow THEN
+    RAISE EXCEPTION '% is %, which is not a timestamp; correct it and run the migrations again',
+        source, quote_literal(value);
+END;
+$$ LANGUAGE plpgsql;
+
+ALTER TABLE live_sets
+    ALTER COLUMN aired_at TYPE TIMESTAMPTZ
+        USING pg_temp.parse_timestamptz(NULLIF(trim(aired_at), ''), 'live_sets ' || id || ' aired_at');
@@ -0,0 +1,15 @@
+-- published_at was copied verbatim from the front matter, so the article listings
+-- ordered it as a string. Convert it to TIMESTAMPTZ. The files are the source of
+-- truth, so a value that does not parse is cleared here and reported by the next
+-- sync, which leaves that article as it is until its file is corrected.
+
+CREATE FUNCTION pg_temp.parse_published_at(value TEXT) RETURNS TIMESTAMPTZ AS $$
+BEGIN
+    RETURN value::TIMESTAMPTZ;
+EXCEPTION WHEN invalid_datetime_format OR datetime_field_overflow THEN
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+ALTER TABLE articles
+    ALTER COLUMN published_at TYPE TIMESTAMPTZ USING pg_temp.parse_published_at(published_at);
@@ -0,0 +1,647 @@
+use std::collections::HashMap;
+
+use axum::extract::{Form, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::routing::{get, post};
+use axum::Router;
+use minijinja::context;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+use sqlx::types::Json;
+use sqlx::PgConnection;
+
+use crate::artists;
+use crate::audit;
+use crate::auth::{CurrentUser, Role};
+use crate::error::{AppError, Path, Query};
+use crate::etag::IfMatch;
+use crate::keys::TONES;
+use crate::models::{NewAlbum, NewArtist, NewBatch, NewEntrega, NewTrack};
+use crate::routes::AppState;
+use crate::spreadsheet;
+use crate::templates;
+use crate::trash::{self, Kind, KINDS};
+use crate::validate::{FieldError, Validate};
+
+/// Rows per list page.
+const PAGE_SIZE: i64 = 50;
+
+#[derive(Clone, Copy)]
+enum Input {
+    Text,
+    Integer,
+    Decimal,
+    Tone,
+    /// Free text suggesting the names of existing artists.
+    ArtistName,
+    /// Dropdown of the live records of a kind.
+    Reference(Kind),
+}
+
+impl Input {
+    /// How `form.html` renders the field.
+    fn widget(self) -> &'static str {
+        match self {
+            Input::Text | Input::ArtistName => "text",
+            Input::Integer => "integer",
+            Input::Decimal => "decimal",
+            Input::Tone | Input::Reference(_) => "select",
+        }
+    }
+}
+
+/// An editable column and how the admin forms present it.
+struct Field {
+    name: &'static str,
+    label: &'static str,
+    input: Input,
+    required: bool,
+}
+
+const fn required(name: &'static str, label: &'static str, input: Input) -> Field {
+    Field {
+        name,
+        label,
+        input,
+        required: true,
+    }
+}
+
+const fn optional(name: &'static str, label: &'static str, input: Input) -> Field {
+    Field {
+        name,
+        label,
+        input,
+        required: false,
+    }
+}
+
+const ARTIST_FIELDS: &[Field] = &[
+    required("name", "Name", Input::Text),
+    optional("country", "Country", Input::Text),
+];
+
+const ALBUM_FIELDS: &[Field] = &[
+    required("title", "Title", Input::Text),
+    optional("artist_id", "Artist", Input::Reference(Kind::Artist)),
+    optional("release_year", "Release year", Input::Integer),
+    optional("label", "Label", Input::Text),
+    optional("format", "Format", Input::Text),
+    optional("country", "Country", Input::Text),
+    optional("genre", "Genre", Input::Text),
+    optional("style", "Style", Input::Text),
+];
+
+const TRACK_FIELDS: &[Field] = &[
+    required("title", "Title", Input::Text),
+    required("artist_name", "Artist", Input::ArtistName),
+    optional("album_id", "Album", Input::Reference(Kind::Album)),
+    optional("position", "Position", Input::Text),
+    optional("duration_seconds", "Duration (seconds)", Input::Integer),
+    optional("bpm", "BPM", Input::Decimal),
+    optional("tone", "Tone", Input::Tone),
+    optional("score", "Score", Input::Text),
+    optional("entrega_id", "Entrega", Input::Reference(Kind::Entrega)),
+];
+
+const BATCH_FIELDS: &[Field] = &[required("name", "Name", Input::Text)];
+
+const ENTREGA_FIELDS: &[Field] = &[
+    required("name", "Name", Input::Text),
+    optional("batch_id", "Batch", Input::Reference(Kind::Batch)),
+];
+
+fn fields(kind: Kind) -> &'static [Field] {
+    match kind {
+        Kind::Artist => ARTIST_FIELDS,
+        Kind::Album => ALBUM_FIELDS,
+        Kind::Track => TRACK_FIELDS,
+        Kind::Batch => BATCH_FIELDS,
+        Kind::Entrega => ENTREGA_FIELDS,
+    }
+}
+
+fn title(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Artist => "Artists",
+        Kind::Album => "Albums",
+        Kind::Track => "Tracks",
+        Kind::Batch => "Batches",
+        Kind::Entrega => "Entregas",
+    }
+}
+
+/// Admin pages are addressed by table name, e.g. `/admin/albums`.
+fn resource_kind(resource: &str) -> Result<Kind, AppError> {
+    Kind::from_table(resource).ok_or_else(|| {
+        AppError::new(
+            StatusCode::NOT_FOUND,
+            "not_found",
+            format!("There is no admin page for `{resource}`"),
+        )
+    })
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/admin", get(dashboard))
+        .route("/admin/{resource}", get(list).post(create))
+        .route("/admin/{resource}/new", get(new_form))
+        .route("/admin/{resource}/{id}", post(update))
+        .route("/admin/{resource}/{id}/edit", get(edit_form))
+        .route("/admin/{resource}/{id}/delete", post(remove))
+}
+
+fn render(
+    name: &str,
+    user: &CurrentUser,
+    page: minijinja::Value,
+) -> Result<Html<String>, AppError> {
+    let admin_nav: Vec<_> = KINDS
+        .into_iter()
+        .map(|kind| context! { path => kind.table(), title => title(kind) })
+        .collect();
+    let ctx = context! {
+        username => &user.username,
+        can_edit => user.role >= Role::Editor,
+        admin_nav => admin_nav,
+        ..page
+    };
+
+    templates::render(name, ctx)
+}
+
+#[derive(Serialize)]
+struct Choice {
+    value: String,
+    label: String,
+}
+
+/// Options of a dropdown, or the suggestions of an artist name.
+async fn choices(conn: &mut PgConnection, input: Input) -> Result<Vec<Choice>, sqlx::Error> {
+    let (value, kind) = match input {
+        Input::Tone => {
+            return Ok(TONES
+                .iter()
+                .map(|tone| Choice {
+                    value: tone.to_string(),
+                    label: tone.to_string(),
+                })
+                .collect());
+        }
+        Input::ArtistName => ("name", Kind::Artist),
+        Input::Reference(kind) => ("id::text", kind),
+        Input::Text | Input::Integer | Input::Decimal => return Ok(Vec::new()),
+    };
+
+    let rows: Vec<(String, String)> = sqlx::query_as(&format!(
+        "SELECT {value} AS value, {label}::text AS label FROM {table} WHERE deleted_at IS NULL ORDER BY label, id",
+        label = kind.label_column(),
+        table = kind.table(),
+    ))
+    .fetch_all(conn)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(value, label)| Choice { value, label })
+        .collect())
+}
+
+/// A JSON column value as typed into a form field.
+fn value_text(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(text)) => text.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+async fn dashboard(
+    State(state): State<AppState>,
+    user: CurrentUser,
+) -> Result<Html<String>, AppError> {
+    let mut resources = Vec::new();
+    for kind in KINDS {
+        let count: i64 = sqlx::query_scalar(&format!(
+            "SELECT count(*) FROM {} WHERE deleted_at IS NULL",
+            kind.table()
+        ))
+        .fetch_one(&state.pool)
+        .await?;
+        resources.push(context! { path => kind.table(), title => title(kind), count => count });
+    }
+
+    render(
+        "admin/dashboard.html",
+        &user,
+        context! { resources => resources },
+    )
+}
+
+#[derive(Deserialize)]
+struct ListParams {
+    page: Option<i64>,
+}
+
+async fn list(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(resource): Path<String>,
+    Query(params): Query<ListParams>,
+) -> Result<Html<String>, AppError> {
+    let kind = resource_kind(&resource)?;
+    let page = params.page.unwrap_or(1).max(1);
+    let mut conn = state.pool.acquire().await?;
+
+    let mut rows: Vec<Value> = sqlx::query_scalar(&format!(
+        "SELECT to_jsonb(t) FROM {} t WHERE deleted_at IS NULL ORDER BY {}, id LIMIT $1 OFFSET $2",
+        kind.table(),
+        kind.label_column()
+    ))
+    .bind(PAGE_SIZE + 1)
+    .bind((page - 1) * PAGE_SIZE)
+    .fetch_all(&mut *conn)
+    .await?;
+    let has_next = rows.len() as i64 > PAGE_SIZE;
+    rows.truncate(PAGE_SIZE as usize);
+
+    // Show references by name rather than by id, looking up only those on the page
+    let mut labels: HashMap<&str, HashMap<i64, String>> = HashMap::new();
+    for field in fields(kind) {
+        if let Input::Reference(referenced) = field.input {
+            let ids = rows
+                .iter()
+                .map(|row| row.get(field.name).and_then(Value::as_i64));
+            labels.insert(
+                field.name,
+                spreadsheet::labels(&state.pool, referenced, ids).await?,
+            );
+        }
+    }
+
+    let rows: Vec<_> = rows
+        .iter()
+        .map(|row| {
+            let cells: Vec<String> = fields(kind)
+                .iter()
+                .map(|field| {
+                    let label = labels.get(field.name).and_then(|labels| {
+                        labels.get(&row.get(field.name).and_then(Value::as_i64)?)
+                    });
+                    match label {
+                        Some(label) => label.clone(),
+                        None => value_text(row.get(field.name)),
+                    }
+                })
+                .collect();
+            context! {
+                id => row.get("id"),
+                version => row.get("version"),
+                cells => cells,
+            }
+        })
+        .collect();
+    let columns: Vec<&str> = fields(kind).iter().map(|field| field.label).collect();
+
+    render(
+        "admin/list.html",
+        &user,
+        context! {
+            path => kind.table(),
+            title => title(kind),
+            columns => columns,
+            rows => rows,
+            page => page,
+            has_next => has_next,
+        },
+    )
+}
+
+/// Form contents being created or edited, kept to show them again when saving fails.
+struct Draft {
+    id: Option<i64>,
+    version: Option<i32>,
+    values: HashMap<String, String>,
+}
+
+async fn render_form(
+    state: &AppState,
+    user: &CurrentUser,
+    kind: Kind,
+    draft: &Draft,
+    failure: Option<&AppError>,
+) -> Result<Html<String>, AppError> {
+    let mut errors = failure
+        .map(|error| error.errors.clone())
+        .unwrap_or_default();
+    // Database errors name at most one field and carry no list
+    if let Some(error) = failure.filter(|error| error.errors.is_empty()) {
+        errors.push(FieldError {
+            field: error.field.clone().unwrap_or_default(),
+            message: error.message.clone(),
+        });
+    }
+
+    let mut conn = state.pool.acquire().await?;
+    let mut inputs = Vec::new();
+    for field in fields(kind) {
+        let error = errors
+            .iter()
+            .find(|error| error.field == field.name)
+            .map(|error| error.message.clone());
+        inputs.push(context! {
+            name => field.name,
+            label => field.label,
+            widget => field.input.widget(),
+            required => field.required,
+            value => draft.values.get(field.name).map(String::as_str).unwrap_or_default(),
+            options => choices(&mut conn, field.input).await?,
+            error => error,
+        });
+    }
+    // Errors on no form field, e.g. a version conflict
+    let general: Vec<String> = errors
+        .iter()
+        .filter(|error| fields(kind).iter().all(|field| field.name != error.field))
+        .map(|error| {
+            if error.field.is_empty() {
+                error.message.clone()
+            } else {
+                error.to_string()
+            }
+        })
+        .collect();
+
+    render(
+        "admin/form.html",
+        user,
+        context! {
+            path => kind.table(),
+            title => title(kind),
+            resource => kind.resource(),
+            id => draft.id,
+            version => draft.version,
+            fields => inputs,
+            errors => general,
+        },
+    )
+}
+
+async fn new_form(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(resource): Path<String>,
+) -> Result<Html<String>, AppError> {
+    let kind = resource_kind(&resource)?;
+    let draft = Draft {
+        id: None,
+        version: None,
+        values: HashMap::new(),
+    };
+
+    render_form(&state, &user, kind, &draft, None).await
+}
+
+async fn edit_form(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path((resource, id)): Path<(String, i64)>,
+) -> Result<Html<String>, AppError> {
+    let kind = resource_kind(&resource)?;
+    let row: Value = sqlx::query_scalar(&format!(
+        "SELECT to_jsonb(t) FROM {} t WHERE id = $1 AND deleted_at IS NULL",
+        kind.table()
+    ))
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found(kind.resource(), id))?;
+
+    let draft = Draft {
+        id: Some(id),
+        version: row
+            .get("version")
+            .and_then(Value::as_i64)
+            .and_then(|version| version.try_into().ok()),
+        values: fields(kind)
+            .iter()
+            .map(|field| (field.name.to_string(), value_text(row.get(field.name))))
+            .collect(),
+    };
+
+    render_form(&state, &user, kind, &draft, None).await
+}
+
+async fn create(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path(resource): Path<String>,
+    Form(values): Form<HashMap<String, String>>,
+) -> Result<Response, AppError> {
+    let kind = resource_kind(&resource)?;
+    let draft = Draft {
+        id: None,
+        version: None,
+        values,
+    };
+
+    submit(&state, &user, kind, &draft).await
+}
+
+async fn update(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path((resource, id)): Path<(String, i64)>,
+    Form(values): Form<HashMap<String, String>>,
+) -> Result<Response, AppError> {
+    let kind = resource_kind(&resource)?;
+    let draft = Draft {
+        id: Some(id),
+        version: Some(form_version(&values)?),
+        values,
+    };
+
+    submit(&state, &user, kind, &draft).await
+}
+
+/// Save the draft and go back to the list, or show the form again with what went wrong.
+async fn submit(
+    state: &AppState,
+    user: &CurrentUser,
+    kind: Kind,
+    draft: &Draft,
+) -> Result<Response, AppError> {
+    match save(state, user, kind, draft).await {
+        Ok(()) => Ok(Redirect::to(&format!("/admin/{}", kind.table())).into_response()),
+        Err(error) => {
+            let page = render_form(state, user, kind, draft, Some(&error)).await?;
+            Ok((error.status, page).into_response())
+        }
+    }
+}
+
+async fn save(
+    state: &AppState,
+    user: &CurrentUser,
+    kind: Kind,
+    draft: &Draft,
+) -> Result<(), AppError> {
+    let record = parse_record(kind, &draft.values)?;
+    let columns: Vec<&str> = fields(kind).iter().map(|field| field.name).collect();
+    let table = kind.table();
+
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+    let id = match (draft.id, draft.version) {
+        (Some(id), Some(version)) => {
+            IfMatch::version(version)
+                .check(&mut tx, table, kind.resource(), id)
+                .await?;
+
+            let assignments: Vec<String> = columns
+                .iter()
+                .map(|column| format!("{column} = r.{column}"))
+                .collect();
+            let result = sqlx::query(&format!(
+                "UPDATE {table} SET {}, updated_at = now()
+                 FROM jsonb_populate_record(NULL::{table}, $1) r
+                 WHERE {table}.id = $2 AND {table}.deleted_at IS NULL",
+                assignments.join(", ")
+            ))
+            .bind(Json(&record))
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+            if result.rows_affected() == 0 {
+                return Err(AppError::not_found(kind.resource(), id));
+            }
+
+            if kind == Kind::Artist {
+                artists::rename_main_credits(&mut tx, id, &value_text(record.get("name"))).await?;
+            }
+            id
+        }
+        _ => {
+            let columns = columns.join(", ");
+            sqlx::query_scalar(&format!(
+                "INSERT INTO {table} ({columns})
+                 SELECT {columns} FROM jsonb_populate_record(NULL::{table}, $1)
+                 RETURNING id"
+            ))
+            .bind(Json(&record))
+            .fetch_one(&mut *tx)
+            .await?
+        }
+    };
+
+    if kind == Kind::Track {
+        artists::sync_main_artist(&mut tx, id, &value_text(record.get("artist_name"))).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Turn form strings into a JSON record of the resource's columns and validate it
+/// like the API would. Blank optional fields become `null`.
+fn parse_record(
+    kind: Kind,
+    values: &HashMap<String, String>,
+) -> Result<Map<String, Value>, Vec<FieldError>> {
+    let mut record = Map::new();
+    let mut errors = Vec::new();
+
+    for field in fields(kind) {
+        let text = values
+            .get(field.name)
+            .map(|value| value.trim())
+            .unwrap_or_default();
+        let value = match field.input {
+            // Left as "" so validation reports the blank required field
+            _ if text.is_empty() && field.required => Value::from(""),
+            _ if text.is_empty() => Value::Null,
+            Input::Integer | Input::Reference(_) => match text.parse::<i64>() {
+                Ok(number) => Value::from(number),
+                Err(_) => {
+                    errors.push(FieldError {
+                        field: field.name.to_string(),
+                        message: "must be a whole number".to_string(),
+                    });
+                    continue;
+                }
+            },
+            Input::Decimal => match text.parse().ok().and_then(Number::from_f64) {
+                Some(number) => Value::Number(number),
+                None => {
+                    errors.push(FieldError {
+                        field: field.name.to_string(),
+                        message: "must be a number".to_string(),
+                    });
+                    continue;
+                }
+            },
+            Input::Text | Input::Tone | Input::ArtistName => Value::from(text),
+        };
+        record.insert(field.name.to_string(), value);
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    match kind {
+        Kind::Artist => validate_as::<NewArtist>(&record),
+        Kind::Album => validate_as::<NewAlbum>(&record),
+        Kind::Track => validate_as::<NewTrack>(&record),
+        Kind::Batch => validate_as::<NewBatch>(&record),
+        Kind::Entrega => validate_as::<NewEntrega>(&record),
+    }?;
+
+    Ok(record)
+}
+
+fn validate_as<T: DeserializeOwned + Validate>(
+    record: &Map<String, Value>,
+) -> Result<(), Vec<FieldError>> {
+    let input: T = serde_json::from_value(Value::Object(record.clone())).map_err(|error| {
+        vec![FieldError {
+            field: String::new(),
+            message: error.to_string(),
+        }]
+    })?;
+    input.validate()
+}
+
+/// The version the form was loaded at, sent back in a hidden field.
+fn form_version(values: &HashMap<String, String>) -> Result<i32, AppError> {
+    values
+        .get("version")
+        .and_then(|version| version.parse().ok())
+        .ok_or_else(|| {
+            AppError::bad_request("The form is missing its version; reload the page and retry")
+                .with_field("version")
+        })
+}
+
+async fn remove(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Path((resource, id)): Path<(String, i64)>,
+    Form(values): Form<HashMap<String, String>>,
+) -> Result<Redirect, AppError> {
+    let kind = resource_kind(&resource)?;
+    let version = form_version(&values)?;
+
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+    IfMatch::version(version)
+        .check(&mut tx, kind.table(), kind.resource(), id)
+        .await?;
+    if !trash::soft_delete(&mut tx, kind, id).await? {
+        return Err(AppError::not_found(kind.resource(), id));
+    }
+    tx.commit().await?;
+
+    Ok(Redirect::to(&format!("/admin/{}", kind.table())))
+}
@@ -0,0 +1,199 @@
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::audit;
+use crate::db::DbPool;
+use crate::models::date_or_timestamp;
+
+/// Metadata read from the optional front matter block at the top of an article:
+///
+/// ```text
+/// ---
+/// title: Fricciones Orgánicas
+/// author: Sonido Sigiloso
+/// status: published
+/// published_at: 2026-02-26T00:00:00Z
+/// entrega: Primera Entrega
+/// ---
+/// ```
+///
+/// `entrega` accepts either the entrega name or its id, and `published_at` an RFC 3339
+/// timestamp or a plain date.
+#[derive(Debug, Default)]
+pub struct FrontMatter {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub status: Option<String>,
+    pub published_at: Option<String>,
+    pub entrega: Option<String>,
+}
+
+impl FrontMatter {
+    /// The article's status, `draft` when it is missing or not one we know.
+    pub fn status(&self) -> &str {
+        self.status
+            .as_deref()
+            .filter(|status| ["draft", "published", "archived"].contains(status))
+            .unwrap_or("draft")
+    }
+
+    /// The publication date, `Err` with the raw value when it does not parse.
+    pub fn published_at(&self) -> Result<Option<DateTime<Utc>>, &str> {
+        match self.published_at.as_deref() {
+            Some(value) => date_or_timestamp(value).map(Some).ok_or(value),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Outcome of [`sync_articles`].
+#[derive(Debug, Serialize)]
+pub struct Synced {
+    pub articles: usize,      // Files on disk
+    pub skipped: Vec<String>, // Files left as last synced because their front matter is invalid
+}
+
+/// Split an article into its front matter and Markdown body.
+pub fn parse_front_matter(content: &str) -> (FrontMatter, &str) {
+    let mut front_matter = FrontMatter::default();
+
+    let Some(after_open) = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+    else {
+        return (front_matter, content);
+    };
+    let Some(end) = after_open.find("\n---") else {
+        return (front_matter, content);
+    };
+
+    for line in after_open[..end].lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"').to_string();
+        if value.is_empty() {
+            continue;
+        }
+        match key.trim() {
+            "title" => front_matter.title = Some(value),
+            "author" => front_matter.author = Some(value),
+            "status" => front_matter.status = Some(value),
+            "published_at" => front_matter.published_at = Some(value),
+            "entrega" => front_matter.entrega = Some(value),
+            _ => {}
+        }
+    }
+
+    let body = &after_open[end + "\n---".len()..];
+    let body = body.split_once('\n').map_or("", |(_, body)| body);
+    (front_matter, body)
+}
+
+/// Title of an article: front matter title, then first `# ` heading, then the slug.
+pub fn article_title(front_matter: &FrontMatter, body: &str, slug: &str) -> String {
+    front_matter
+        .title
+        .clone()
+        .or_else(|| {
+            body.lines()
+                .find_map(|line| line.strip_prefix("# "))
+                .map(|title| title.trim().to_string())
+        })
+        .unwrap_or_else(|| slug.to_string())
+}
+
+/// Synchronize the `articles` table with the Markdown files in `articles_dir`.
+/// New files are inserted, existing ones updated by slug, and rows whose file
+/// disappeared are removed. The Markdown body is stored for full-text search.
+/// A file whose `published_at` does not parse is reported and skipped, keeping
+/// the row as it was last synced. Changes are audited under `actor`.
+pub async fn sync_articles(
+    pool: &DbPool,
+    articles_dir: &Path,
+    actor: &str,
+) -> Result<Synced, Box<dyn std::error::Error + Send + Sync>> {
+    let mut entries = tokio::fs::read_dir(articles_dir).await?;
+    let mut tx = audit::begin(pool, actor).await?;
+    let mut slugs: HashSet<String> = HashSet::new();
+    let mut skipped = Vec::new();
+
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension() != Some(OsStr::new("md")) {
+            continue;
+        }
+        let (Some(filename), Some(slug)) = (
+            path.file_name().and_then(OsStr::to_str),
+            path.file_stem().and_then(OsStr::to_str),
+        ) else {
+            continue;
+        };
+
+        let content = tokio::fs::read_to_string(&path).await?;
+        let (front_matter, body) = parse_front_matter(&content);
+        slugs.insert(slug.to_string());
+        let published_at = match front_matter.published_at() {
+            Ok(published_at) => published_at,
+            Err(value) => {
+                eprintln!(
+                    "⚠️  Skipping {filename}: published_at `{value}` is not a date (YYYY-MM-DD) or an RFC 3339 timestamp"
+                );
+                skipped.push(filename.to_string());
+                continue;
+            }
+        };
+        let title = article_title(&front_matter, body, slug);
+        let status = front_matter.status();
+
+        let entrega_id: Option<i64> =
+            match &front_matter.entrega {
+                Some(reference) => sqlx::query_scalar(
+                    "SELECT id FROM entregas WHERE (name = $1 OR id::text = $1) AND deleted_at IS NULL ORDER BY id LIMIT 1",
+                )
+                .bind(reference)
+                .fetch_optional(&mut *tx)
+                .await?,
+                None => None,
+            };
+
+        sqlx::query(
+            "INSERT INTO articles (slug, filename, title, author, status, published_at, entrega_id, body)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+             ON CONFLICT (slug) DO UPDATE SET filename = EXCLUDED.filename, title = EXCLUDED.title,
+                 author = EXCLUDED.author, status = EXCLUDED.status,
+                 published_at = EXCLUDED.published_at, entrega_id = EXCLUDED.entrega_id,
+                 body = EXCLUDED.body, updated_at = now()
+             -- Only touch updated_at when the file actually changed
+             WHERE (articles.filename, articles.title, articles.author, articles.status, articles.published_at, articles.entrega_id, articles.body)
+                 IS DISTINCT FROM (EXCLUDED.filename, EXCLUDED.title, EXCLUDED.author, EXCLUDED.status, EXCLUDED.published_at, EXCLUDED.entrega_id, EXCLUDED.body)",
+        )
+        .bind(slug)
+        .bind(filename)
+        .bind(&title)
+        .bind(&front_matter.author)
+        .bind(status)
+        .bind(published_at)
+        .bind(entrega_id)
+        .bind(body)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let slugs: Vec<String> = slugs.into_iter().collect();
+    sqlx::query("DELETE FROM articles WHERE NOT (slug = ANY($1))")
+        .bind(&slugs)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(Synced {
+        articles: slugs.len(),
+        skipped,
+    })
+}
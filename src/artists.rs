@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+
+use sqlx::PgConnection;
+
+use crate::db::DbPool;
+use crate::models::{Artist, ArtistMerge, DuplicateGroup};
+use crate::normalize::{normalize_artist_name, similarity};
+
+/// Placeholder credit used by the `tracks.artist_name` column default.
+pub const UNKNOWN_ARTIST: &str = "Unknown";
+
+/// Find an artist by name, creating it when missing. Returns the artist id.
+///
+/// An exact match wins; otherwise spelling variants that normalize to the same key
+/// (`"fred  fresh"` for `"Fred Fresh"`) reuse the existing artist instead of adding a duplicate.
+pub async fn find_or_create_artist(
+    conn: &mut PgConnection,
+    name: &str,
+) -> Result<i64, sqlx::Error> {
+    let existing: Option<i64> = sqlx::query_scalar(
+        "SELECT id FROM artists WHERE name = $1 AND deleted_at IS NULL ORDER BY id LIMIT 1",
+    )
+    .bind(name)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if let Some(id) = existing {
+        return Ok(id);
+    }
+
+    let key = normalize_artist_name(name);
+    let artists: Vec<(i64, String)> =
+        sqlx::query_as("SELECT id, name FROM artists WHERE deleted_at IS NULL ORDER BY id")
+            .fetch_all(&mut *conn)
+            .await?;
+    if let Some((id, _)) = artists
+        .into_iter()
+        .find(|(_, existing)| normalize_artist_name(existing) == key)
+    {
+        return Ok(id);
+    }
+
+    sqlx::query_scalar("INSERT INTO artists (name) VALUES ($1) RETURNING id")
+        .bind(name)
+        .fetch_one(&mut *conn)
+        .await
+}
+
+/// Mirror a track's `artist_name` as its `main` credit in `track_artists`.
+/// Other roles (featuring, remixer, producer) are left untouched.
+pub async fn sync_main_artist(
+    conn: &mut PgConnection,
+    track_id: i64,
+    artist_name: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM track_artists WHERE track_id = $1 AND role = 'main'")
+        .bind(track_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let artist_name = artist_name.trim();
+    if artist_name.is_empty() || artist_name == UNKNOWN_ARTIST {
+        return Ok(());
+    }
+
+    let artist_id = find_or_create_artist(conn, artist_name).await?;
+    sqlx::query(
+        "INSERT INTO track_artists (track_id, artist_id, role) VALUES ($1, $2, 'main') ON CONFLICT DO NOTHING",
+    )
+    .bind(track_id)
+    .bind(artist_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Keep the `artist_name` of tracks mainly credited to an artist in step with its new name.
+pub async fn rename_main_credits(
+    conn: &mut PgConnection,
+    artist_id: i64,
+    name: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE tracks SET artist_name = $1, updated_at = now()
+         WHERE id IN (SELECT track_id FROM track_artists WHERE artist_id = $2 AND role = 'main')
+           AND artist_name <> $1",
+    )
+    .bind(name)
+    .bind(artist_id)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Group artists whose names normalize to the same key, plus pairs of keys at least
+/// `threshold` similar. Groups are sorted from most to least similar.
+pub async fn find_duplicates(
+    pool: &DbPool,
+    threshold: f64,
+) -> Result<Vec<DuplicateGroup>, sqlx::Error> {
+    let artists = sqlx::query_as::<_, Artist>(
+        "SELECT id, name, country, created_at, updated_at, version FROM artists WHERE deleted_at IS NULL ORDER BY id",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut by_key: BTreeMap<String, Vec<Artist>> = BTreeMap::new();
+    for artist in artists {
+        by_key
+            .entry(normalize_artist_name(&artist.name))
+            .or_default()
+            .push(artist);
+    }
+
+    let keys: Vec<&String> = by_key.keys().collect();
+    let mut groups = Vec::new();
+
+    for (key, artists) in &by_key {
+        if artists.len() > 1 {
+            groups.push(DuplicateGroup {
+                normalized_names: vec![key.clone()],
+                similarity: 1.0,
+                artists: artists.clone(),
+            });
+        }
+    }
+
+    for (index, a) in keys.iter().enumerate() {
+        for b in &keys[index + 1..] {
+            let score = similarity(a, b);
+            if score >= threshold {
+                groups.push(DuplicateGroup {
+                    normalized_names: vec![a.to_string(), b.to_string()],
+                    similarity: (score * 1000.0).round() / 1000.0,
+                    artists: by_key[*a].iter().chain(&by_key[*b]).cloned().collect(),
+                });
+            }
+        }
+    }
+
+    groups.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    Ok(groups)
+}
+
+/// Merge `source_ids` into `target_id`: track credits and albums are repointed to the
+/// target, tracks mainly credited to it take its name, an `artist_merges` record is
+/// written per source, and the source artists are deleted.
+///
+/// Callers run this inside a transaction so a failed merge leaves nothing behind.
+pub async fn merge_artists(
+    conn: &mut PgConnection,
+    target_id: i64,
+    source_ids: &[i64],
+) -> Result<Vec<ArtistMerge>, sqlx::Error> {
+    let target_name: String =
+        sqlx::query_scalar("SELECT name FROM artists WHERE id = $1 FOR UPDATE")
+            .bind(target_id)
+            .fetch_one(&mut *conn)
+            .await?;
+
+    let mut merges = Vec::new();
+    for &source_id in source_ids {
+        let source = sqlx::query_as::<_, Artist>(
+            "SELECT id, name, country, created_at, updated_at, version FROM artists WHERE id = $1 FOR UPDATE",
+        )
+        .bind(source_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let track_credits_moved: i64 = sqlx::query_scalar(
+            "WITH moved AS (
+                 DELETE FROM track_artists WHERE artist_id = $1 RETURNING track_id, role
+             ), inserted AS (
+                 INSERT INTO track_artists (track_id, artist_id, role)
+                 SELECT track_id, $2, role FROM moved
+                 ON CONFLICT DO NOTHING
+             )
+             SELECT count(*) FROM moved",
+        )
+        .bind(source_id)
+        .bind(target_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let albums_moved = sqlx::query(
+            "UPDATE albums SET artist_id = $1, updated_at = now() WHERE artist_id = $2",
+        )
+        .bind(target_id)
+        .bind(source_id)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as i64;
+
+        sqlx::query("DELETE FROM artists WHERE id = $1")
+            .bind(source_id)
+            .execute(&mut *conn)
+            .await?;
+
+        let merge = sqlx::query_as::<_, ArtistMerge>(
+            "INSERT INTO artist_merges (target_artist_id, source_artist_id, source_name, source_country, track_credits_moved, albums_moved, merged_at)
+             VALUES ($1, $2, $3, $4, $5, $6, now())
+             RETURNING id, target_artist_id, source_artist_id, source_name, source_country, track_credits_moved, albums_moved, merged_at",
+        )
+        .bind(target_id)
+        .bind(source_id)
+        .bind(&source.name)
+        .bind(&source.country)
+        .bind(track_credits_moved)
+        .bind(albums_moved)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        merges.push(merge);
+    }
+
+    rename_main_credits(conn, target_id, &target_name).await?;
+
+    Ok(merges)
+}
@@ -0,0 +1,238 @@
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{FromRow, PgConnection, Postgres, QueryBuilder, Transaction};
+
+use crate::db::DbPool;
+use crate::error::AppError;
+use crate::listing::{ColumnType, Listing, Order, SortColumn};
+use crate::models::AuditFilter;
+
+/// Tables watched by the `audit_row_change` trigger, with the columns that identify a row.
+const AUDITED: [(&str, &[&str]); 9] = [
+    ("artists", &["id"]),
+    ("albums", &["id"]),
+    ("tracks", &["id"]),
+    ("batches", &["id"]),
+    ("entregas", &["id"]),
+    ("live_sets", &["id"]),
+    ("live_set_entries", &["id"]),
+    ("articles", &["id"]),
+    ("track_artists", &["track_id", "artist_id", "role"]),
+];
+
+/// Columns a revert leaves as they are now.
+const BOOKKEEPING: [&str; 3] = ["created_at", "updated_at", "version"];
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub occurred_at: DateTime<Utc>,
+    pub actor: String,
+    pub action: String, // create, update, trash, restore or delete
+    pub table_name: String,
+    pub row_id: Option<i64>, // track_id for track_artists
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+/// Begin a transaction whose writes the audit triggers attribute to `actor`.
+pub async fn begin(
+    pool: &DbPool,
+    actor: &str,
+) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("SELECT set_config('audit.actor', $1, true)")
+        .bind(actor)
+        .execute(&mut *tx)
+        .await?;
+    Ok(tx)
+}
+
+fn key_columns(table: &str) -> Option<&'static [&'static str]> {
+    AUDITED
+        .iter()
+        .find(|(name, _)| *name == table)
+        .map(|(_, keys)| *keys)
+}
+
+/// Reject table names that are not audited, so they never reach the query text.
+pub fn check_table(table: &str) -> Result<(), AppError> {
+    if key_columns(table).is_some() {
+        Ok(())
+    } else {
+        let tables: Vec<&str> = AUDITED.iter().map(|(name, _)| *name).collect();
+        Err(AppError::bad_request(format!(
+            "Unknown table `{table}`, expected one of: {}",
+            tables.join(", ")
+        ))
+        .with_field("table"))
+    }
+}
+
+/// Audit entries matching the filter, newest first.
+pub fn audit_query(filter: &AuditFilter) -> Result<Listing, AppError> {
+    let mut query = QueryBuilder::new(
+        "SELECT id, occurred_at, actor, action, table_name, row_id, before, after FROM audit_log WHERE TRUE",
+    );
+
+    if let Some(table) = &filter.table {
+        check_table(table)?;
+        query.push(" AND table_name = ").push_bind(table.clone());
+    }
+    if let Some(row_id) = filter.row_id {
+        query.push(" AND row_id = ").push_bind(row_id);
+    }
+    if let Some(actor) = &filter.actor {
+        query.push(" AND actor = ").push_bind(actor.clone());
+    }
+    if let Some(action) = &filter.action {
+        query.push(" AND action = ").push_bind(action.clone());
+    }
+    let order = Order::new(vec![SortColumn::new("id", ColumnType::Integer).descending()]);
+
+    Ok(Listing::new(query, order))
+}
+
+/// Every change of one record, oldest first.
+pub async fn history(
+    conn: &mut PgConnection,
+    table: &str,
+    row_id: i64,
+) -> Result<Vec<AuditEntry>, AppError> {
+    check_table(table)?;
+
+    let entries = sqlx::query_as::<_, AuditEntry>(
+        "SELECT id, occurred_at, actor, action, table_name, row_id, before, after FROM audit_log
+         WHERE table_name = $1 AND row_id = $2 ORDER BY id",
+    )
+    .bind(table)
+    .bind(row_id)
+    .fetch_all(conn)
+    .await?;
+
+    Ok(entries)
+}
+
+/// Undo one audited change by putting the row back as it was before it.
+///
+/// A creation is undone by trashing the row (or deleting it where there is no
+/// trash), a hard delete by inserting the old row again, and anything else by
+/// writing back the old values of the columns it changed, provided no later change
+/// touched them. The revert is itself audited.
+pub async fn revert(conn: &mut PgConnection, entry_id: i64) -> Result<AuditEntry, AppError> {
+    let entry = sqlx::query_as::<_, AuditEntry>(
+        "SELECT id, occurred_at, actor, action, table_name, row_id, before, after FROM audit_log WHERE id = $1",
+    )
+    .bind(entry_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| AppError::not_found("Audit entry", entry_id))?;
+
+    let table = entry.table_name.as_str();
+    let keys = key_columns(table).ok_or_else(|| {
+        AppError::unprocessable(format!("Changes to {table} can no longer be reverted"))
+    })?;
+    let key_list = keys.join(", ");
+    let columns = writable_columns(conn, table).await?;
+
+    let result = match (&entry.before, &entry.after) {
+        (None, Some(after)) => {
+            let sql = if columns.iter().any(|column| column == "deleted_at") {
+                format!(
+                    "UPDATE {table} SET deleted_at = now(), updated_at = now()
+                     WHERE ({key_list}) = (SELECT {key_list} FROM jsonb_populate_record(NULL::{table}, $1)) AND deleted_at IS NULL"
+                )
+            } else {
+                format!(
+                    "DELETE FROM {table} WHERE ({key_list}) = (SELECT {key_list} FROM jsonb_populate_record(NULL::{table}, $1))"
+                )
+            };
+            sqlx::query(&sql).bind(after).execute(&mut *conn).await?
+        }
+        (Some(before), None) => {
+            let restored = present_columns(&columns, before, &[]);
+            sqlx::query(&format!(
+                "INSERT INTO {table} ({restored}) SELECT {restored} FROM jsonb_populate_record(NULL::{table}, $1)"
+            ))
+            .bind(before)
+            .execute(&mut *conn)
+            .await?
+        }
+        (Some(before), Some(after)) => {
+            // Only the columns this entry changed, and only while they still hold its result
+            let changed: Vec<&str> = columns
+                .iter()
+                .map(String::as_str)
+                .filter(|column| !keys.contains(column) && !BOOKKEEPING.contains(column))
+                .filter(|column| before.get(*column) != after.get(*column))
+                .collect();
+            if changed.is_empty() {
+                return Err(AppError::unprocessable(format!(
+                    "Audit entry {entry_id} changed no column that can be written back"
+                )));
+            }
+            let expected: serde_json::Map<String, Value> = changed
+                .iter()
+                .map(|column| {
+                    let value = after.get(*column).cloned().unwrap_or(Value::Null);
+                    (column.to_string(), value)
+                })
+                .collect();
+            let restored = changed.join(", ");
+            let touch = if columns.iter().any(|column| column == "updated_at") {
+                ", updated_at = now()"
+            } else {
+                ""
+            };
+            sqlx::query(&format!(
+                "UPDATE {table} AS t SET ({restored}) = (SELECT {restored} FROM jsonb_populate_record(NULL::{table}, $1)){touch}
+                 WHERE ({key_list}) = (SELECT {key_list} FROM jsonb_populate_record(NULL::{table}, $1))
+                   AND to_jsonb(t) @> $2"
+            ))
+            .bind(before)
+            .bind(Value::Object(expected))
+            .execute(&mut *conn)
+            .await?
+        }
+        (None, None) => return Err(AppError::unprocessable("The audit entry records no row")),
+    };
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::new(
+            StatusCode::CONFLICT,
+            "revert_conflict",
+            format!(
+                "Audit entry {entry_id} cannot be reverted: the {table} row is gone or was changed since"
+            ),
+        ));
+    }
+
+    Ok(entry)
+}
+
+/// Columns of `table` that can be written, i.e. all but generated ones.
+async fn writable_columns(conn: &mut PgConnection, table: &str) -> Result<Vec<String>, AppError> {
+    let columns = sqlx::query_scalar(
+        "SELECT column_name::TEXT FROM information_schema.columns
+         WHERE table_schema = current_schema() AND table_name = $1 AND is_generated = 'NEVER'
+         ORDER BY ordinal_position",
+    )
+    .bind(table)
+    .fetch_all(conn)
+    .await?;
+
+    Ok(columns)
+}
+
+/// Comma separated writable columns recorded in `row`, minus `skipped`.
+fn present_columns(columns: &[String], row: &Value, skipped: &[&str]) -> String {
+    columns
+        .iter()
+        .filter(|column| row.get(column.as_str()).is_some())
+        .filter(|column| !skipped.contains(&column.as_str()))
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,412 @@
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use axum::extract::{FromRequestParts, OptionalFromRequestParts, Request, State};
+use axum::http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Redirect, Response};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
+
+use crate::db::DbPool;
+use crate::error::AppError;
+
+pub const SESSION_COOKIE: &str = "session";
+/// How long a login stays valid.
+pub const SESSION_DAYS: i32 = 14;
+
+/// Ordered so that a higher role can do everything a lower one can.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    Reader,
+    Editor,
+    Admin,
+}
+
+pub const ROLES: [Role; 3] = [Role::Reader, Role::Editor, Role::Admin];
+
+impl Role {
+    pub fn parse(name: &str) -> Option<Self> {
+        ROLES.into_iter().find(|role| role.as_str() == name)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Reader => "reader",
+            Role::Editor => "editor",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+/// The caller behind a session cookie or API token, stored in the request extensions.
+#[derive(Debug, Clone)]
+pub struct CurrentUser {
+    pub id: i64,
+    pub username: String,
+    pub role: Role,
+}
+
+#[derive(FromRow)]
+struct UserRow {
+    id: i64,
+    username: String,
+    role: String,
+}
+
+impl From<UserRow> for CurrentUser {
+    fn from(row: UserRow) -> Self {
+        Self {
+            id: row.id,
+            username: row.username,
+            // The users_role_check constraint keeps unknown roles out
+            role: Role::parse(&row.role).unwrap_or(Role::Reader),
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for CurrentUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<CurrentUser>()
+            .cloned()
+            .ok_or_else(unauthenticated)
+    }
+}
+
+/// For public pages that show more to a logged-in caller.
+impl<S: Send + Sync> OptionalFromRequestParts<S> for CurrentUser {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        Ok(parts.extensions.get::<CurrentUser>().cloned())
+    }
+}
+
+pub fn hash_password(password: &str) -> Result<String, AppError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|error| AppError::internal(format!("Could not hash the password: {error}")))
+}
+
+pub fn verify_password(hash: &str, password: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+/// A fresh random secret for a session cookie or an API token.
+pub fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Only this digest of a secret is stored.
+pub fn token_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Whether session cookies are only sent over HTTPS. Off by default, since the
+/// server itself speaks plain HTTP on the LAN; `SESSION_COOKIE_SECURE=true` turns it
+/// on when it is reached through an HTTPS proxy.
+fn secure_cookies() -> bool {
+    std::env::var("SESSION_COOKIE_SECURE").is_ok_and(|value| {
+        matches!(
+            value.trim().to_lowercase().as_str(),
+            "true" | "1" | "yes" | "on"
+        )
+    })
+}
+
+pub fn session_cookie(token: String) -> Cookie<'static> {
+    Cookie::build((SESSION_COOKIE, token))
+        .path("/")
+        .http_only(true)
+        .secure(secure_cookies())
+        // Strict keeps other sites from submitting the admin forms with our cookie
+        .same_site(SameSite::Strict)
+        .max_age(time::Duration::days(SESSION_DAYS.into()))
+        .build()
+}
+
+/// Create the first admin from `ADMIN_USERNAME` and `ADMIN_PASSWORD` when there are no users yet.
+pub async fn ensure_admin(pool: &DbPool) -> Result<(), Box<dyn std::error::Error>> {
+    let users: i64 = sqlx::query_scalar("SELECT count(*) FROM users")
+        .fetch_one(pool)
+        .await?;
+    if users > 0 {
+        return Ok(());
+    }
+
+    let (Ok(username), Ok(password)) = (
+        std::env::var("ADMIN_USERNAME"),
+        std::env::var("ADMIN_PASSWORD"),
+    ) else {
+        println!(
+            "⚠ No users yet: set ADMIN_USERNAME and ADMIN_PASSWORD to create the first admin. Writes are refused until then."
+        );
+        return Ok(());
+    };
+
+    let hash = hash_password(&password).map_err(|error| error.message)?;
+    sqlx::query("INSERT INTO users (username, password_hash, role) VALUES ($1, $2, 'admin')")
+        .bind(&username)
+        .bind(&hash)
+        .execute(pool)
+        .await?;
+    println!("✓ Created admin user {username}");
+
+    Ok(())
+}
+
+/// Role a request needs, or `None` for public routes.
+///
+/// Reads are public except for `/admin`, the audit trail and the trash, which need a
+/// login, and the duplicate artist report, which needs an editor. `/users` and
+/// `/export/snapshot` need an admin and every other mutating request an editor.
+fn required_role(method: &Method, path: &str) -> Option<Role> {
+    let under = |prefix: &str| {
+        path == prefix
+            || path
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.starts_with('/'))
+    };
+    let reads = matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS);
+
+    if path == "/login" || path == "/logout" {
+        None
+    } else if under("/users") || path == "/export/snapshot" {
+        // A snapshot is a copy of the whole catalogue and its history
+        Some(Role::Admin)
+    } else if under("/tokens") {
+        Some(Role::Reader)
+    } else if under("/audit") || under("/history") || under("/trash") {
+        // Audit entries name who did what, and the trash holds records removed on
+        // purpose, so only logged in users see them
+        Some(if reads { Role::Reader } else { Role::Editor })
+    } else if under("/admin") {
+        Some(if reads { Role::Reader } else { Role::Editor })
+    } else if path == "/artists/duplicates" {
+        // Only the editors who merge artists act on it, and it scans every artist pair
+        Some(Role::Editor)
+    } else if reads || path == "/setlists/generate" {
+        // Generating a setlist only computes an answer
+        None
+    } else {
+        Some(Role::Editor)
+    }
+}
+
+/// Middleware resolving the caller and enforcing [`required_role`].
+///
+/// A bearer token must be valid when sent; an expired or unknown session cookie
+/// just leaves the request anonymous.
+pub async fn authorize(State(pool): State<DbPool>, mut request: Request, next: Next) -> Response {
+    let user = match identify(&pool, request.headers()).await {
+        Ok(user) => user,
+        Err(error) => return with_challenge(error),
+    };
+    let required = required_role(request.method(), request.uri().path());
+
+    if let Some(required) = required {
+        match &user {
+            None if request.uri().path().starts_with("/admin") => {
+                return Redirect::to("/login?next=/admin").into_response();
+            }
+            None => return with_challenge(unauthenticated()),
+            Some(user) if user.role < required => {
+                return AppError::new(
+                    StatusCode::FORBIDDEN,
+                    "forbidden",
+                    format!(
+                        "This needs the {} role; {} is {}",
+                        required.as_str(),
+                        user.username,
+                        user.role.as_str()
+                    ),
+                )
+                .into_response();
+            }
+            Some(_) => {}
+        }
+    }
+
+    if let Some(user) = user {
+        request.extensions_mut().insert(user);
+    }
+    next.run(request).await
+}
+
+async fn identify(pool: &DbPool, headers: &HeaderMap) -> Result<Option<CurrentUser>, AppError> {
+    if let Some(header) = headers.get(AUTHORIZATION) {
+        let token = header
+            .to_str()
+            .ok()
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim)
+            .ok_or_else(|| {
+                AppError::new(
+                    StatusCode::UNAUTHORIZED,
+                    "unauthenticated",
+                    "Authorization must be `Bearer <token>`",
+                )
+            })?;
+
+        let user = sqlx::query_as::<_, UserRow>(
+            "UPDATE api_tokens t SET last_used_at = now() FROM users u
+             WHERE u.id = t.user_id AND t.token_hash = $1 AND t.revoked_at IS NULL
+             RETURNING u.id, u.username, u.role",
+        )
+        .bind(token_hash(token))
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::UNAUTHORIZED,
+                "unauthenticated",
+                "The API token is unknown or revoked",
+            )
+        })?;
+
+        return Ok(Some(user.into()));
+    }
+
+    let jar = CookieJar::from_headers(headers);
+    let Some(cookie) = jar.get(SESSION_COOKIE) else {
+        return Ok(None);
+    };
+
+    let user = sqlx::query_as::<_, UserRow>(
+        "SELECT u.id, u.username, u.role FROM sessions s JOIN users u ON u.id = s.user_id
+         WHERE s.token_hash = $1 AND s.expires_at > now()",
+    )
+    .bind(token_hash(cookie.value()))
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(user.map(CurrentUser::from))
+}
+
+fn unauthenticated() -> AppError {
+    AppError::new(
+        StatusCode::UNAUTHORIZED,
+        "unauthenticated",
+        "Log in at /login or send `Authorization: Bearer <token>`",
+    )
+}
+
+fn with_challenge(error: AppError) -> Response {
+    let mut response = error.into_response();
+    if response.status() == StatusCode::UNAUTHORIZED {
+        response
+            .headers_mut()
+            .insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn role(method: Method, path: &str) -> Option<Role> {
+        required_role(&method, path)
+    }
+
+    #[test]
+    fn catalogue_reads_are_public() {
+        for path in [
+            "/",
+            "/albums",
+            "/tracks/3",
+            "/tracks/3/compatible",
+            "/articles/fricciones.md",
+            "/search",
+            "/export/csv",
+        ] {
+            assert_eq!(role(Method::GET, path), None, "{path}");
+        }
+        assert_eq!(role(Method::HEAD, "/albums"), None);
+        assert_eq!(role(Method::POST, "/setlists/generate"), None);
+        assert_eq!(role(Method::POST, "/login"), None);
+        assert_eq!(role(Method::POST, "/logout"), None);
+    }
+
+    #[test]
+    fn writes_need_an_editor() {
+        assert_eq!(role(Method::POST, "/albums"), Some(Role::Editor));
+        assert_eq!(role(Method::PATCH, "/tracks/3"), Some(Role::Editor));
+        assert_eq!(
+            role(Method::DELETE, "/tracks/3/artists/1"),
+            Some(Role::Editor)
+        );
+        assert_eq!(role(Method::POST, "/import/json"), Some(Role::Editor));
+        assert_eq!(role(Method::POST, "/articles/sync"), Some(Role::Editor));
+        assert_eq!(role(Method::POST, "/audit/7/revert"), Some(Role::Editor));
+        assert_eq!(role(Method::POST, "/admin/albums/new"), Some(Role::Editor));
+        assert_eq!(role(Method::GET, "/artists/duplicates"), Some(Role::Editor));
+    }
+
+    #[test]
+    fn history_and_admin_pages_need_a_login() {
+        assert_eq!(role(Method::GET, "/admin"), Some(Role::Reader));
+        assert_eq!(role(Method::GET, "/admin/tracks"), Some(Role::Reader));
+        assert_eq!(role(Method::GET, "/audit"), Some(Role::Reader));
+        assert_eq!(role(Method::GET, "/history/tracks/3"), Some(Role::Reader));
+        assert_eq!(role(Method::GET, "/trash"), Some(Role::Reader));
+        assert_eq!(
+            role(Method::POST, "/trash/album/4/restore"),
+            Some(Role::Editor)
+        );
+        assert_eq!(role(Method::GET, "/tokens"), Some(Role::Reader));
+        assert_eq!(role(Method::DELETE, "/tokens/2"), Some(Role::Reader));
+    }
+
+    #[test]
+    fn accounts_and_snapshots_need_an_admin() {
+        assert_eq!(role(Method::GET, "/users"), Some(Role::Admin));
+        assert_eq!(role(Method::PATCH, "/users/2"), Some(Role::Admin));
+        assert_eq!(role(Method::GET, "/export/snapshot"), Some(Role::Admin));
+    }
+
+    #[test]
+    fn prefixes_match_whole_segments() {
+        assert_eq!(role(Method::GET, "/administrators"), None);
+        assert_eq!(role(Method::GET, "/usersettings"), None);
+        assert_eq!(role(Method::GET, "/auditions"), None);
+    }
+
+    #[test]
+    fn tokens_are_random_and_stored_hashed() {
+        let token = new_token();
+        assert_eq!(token.len(), 64);
+        assert_ne!(token, new_token());
+        assert_eq!(token_hash(&token), token_hash(&token));
+        assert_ne!(token_hash(&token), token);
+    }
+
+    #[test]
+    fn passwords_verify_against_their_hash() {
+        let hash = hash_password("a long password").unwrap();
+        assert!(verify_password(&hash, "a long password"));
+        assert!(!verify_password(&hash, "another password"));
+        assert!(!verify_password("not a hash", "a long password"));
+    }
+}
@@ -0,0 +1,240 @@
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use axum::extract::{FromRequest, FromRequestParts};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::postgres::PgDatabaseError;
+
+use crate::validate::FieldError;
+
+/// Error returned by every handler, rendered as
+/// `{"error": {"code", "message", "field", "constraint", "errors"}}` with a matching status.
+#[derive(Debug)]
+pub struct AppError {
+    pub status: StatusCode,
+    pub code: &'static str,
+    pub message: String,
+    pub field: Option<String>,
+    pub constraint: Option<String>,
+    pub errors: Vec<FieldError>, // Every failing field of a rejected record
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    code: &'a str,
+    message: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    constraint: Option<&'a str>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    errors: &'a [FieldError],
+}
+
+impl AppError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+            field: None,
+            constraint: None,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "bad_request", message)
+    }
+
+    pub fn not_found(resource: &str, id: i64) -> Self {
+        Self::new(
+            StatusCode::NOT_FOUND,
+            "not_found",
+            format!("{resource} {id} not found"),
+        )
+    }
+
+    pub fn unprocessable(message: impl Into<String>) -> Self {
+        Self::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "unprocessable_entity",
+            message,
+        )
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "internal_error", message)
+    }
+
+    pub fn with_field(mut self, field: impl Into<String>) -> Self {
+        self.field = Some(field.into());
+        self
+    }
+
+    fn with_constraint(mut self, constraint: Option<&str>) -> Self {
+        self.constraint = constraint.map(str::to_string);
+        self
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            code: self.code,
+            message: &self.message,
+            field: self.field.as_deref(),
+            constraint: self.constraint.as_deref(),
+            errors: &self.errors,
+        };
+
+        (self.status, axum::Json(json!({ "error": body }))).into_response()
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(error: sqlx::Error) -> Self {
+        let sqlx::Error::Database(db_error) = &error else {
+            if matches!(error, sqlx::Error::RowNotFound) {
+                return Self::new(StatusCode::NOT_FOUND, "not_found", "Record not found");
+            }
+            eprintln!("❌ Database error: {error}");
+            return Self::internal("Database error");
+        };
+
+        let Some(pg_error) = db_error.try_downcast_ref::<PgDatabaseError>() else {
+            eprintln!("❌ Database error: {error}");
+            return Self::internal("Database error");
+        };
+
+        let constraint = pg_error.constraint();
+        let field = pg_error
+            .column()
+            .map(str::to_string)
+            .or_else(|| constraint_field(pg_error.table(), constraint));
+        let detail = pg_error.detail().unwrap_or(pg_error.message());
+
+        let app_error = match pg_error.code() {
+            // unique_violation
+            "23505" => Self::new(StatusCode::CONFLICT, "unique_violation", detail),
+            // foreign_key_violation: either the referenced row is missing or it is still in use
+            "23503" if detail.contains("is still referenced") => {
+                Self::new(StatusCode::CONFLICT, "still_referenced", detail)
+            }
+            "23503" => Self::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "foreign_key_violation",
+                detail,
+            ),
+            // check_violation
+            "23514" => Self::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "check_violation",
+                pg_error.message(),
+            ),
+            // not_null_violation
+            "23502" => Self::new(
+                StatusCode::BAD_REQUEST,
+                "not_null_violation",
+                pg_error.message(),
+            ),
+            // invalid_text_representation, datetime_field_overflow, invalid_datetime_format
+            "22P02" | "22008" | "22007" => {
+                Self::new(StatusCode::BAD_REQUEST, "invalid_value", pg_error.message())
+            }
+            // numeric_value_out_of_range
+            "22003" => Self::new(StatusCode::BAD_REQUEST, "out_of_range", pg_error.message()),
+            _ => {
+                eprintln!("❌ Database error: {error}");
+                Self::internal("Database error")
+            }
+        };
+
+        Self {
+            field,
+            ..app_error.with_constraint(constraint)
+        }
+    }
+}
+
+impl From<Vec<FieldError>> for AppError {
+    fn from(errors: Vec<FieldError>) -> Self {
+        let message = match errors.as_slice() {
+            [error] => error.to_string(),
+            _ => format!("{} fields failed validation", errors.len()),
+        };
+        let field = match errors.as_slice() {
+            [error] => Some(error.field.clone()),
+            _ => None,
+        };
+
+        Self {
+            field,
+            errors,
+            ..Self::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "validation_failed",
+                message,
+            )
+        }
+    }
+}
+
+impl From<JsonRejection> for AppError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::new(rejection.status(), "invalid_body", rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for AppError {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::new(rejection.status(), "invalid_query", rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for AppError {
+    fn from(rejection: PathRejection) -> Self {
+        Self::new(rejection.status(), "invalid_path", rejection.body_text())
+    }
+}
+
+/// Field named by a PostgreSQL constraint such as `tracks_tone_check`,
+/// `tracks_entrega_id_fkey` or `articles_slug_key`.
+fn constraint_field(table: Option<&str>, constraint: Option<&str>) -> Option<String> {
+    let constraint = constraint?;
+    let without_table = match table {
+        Some(table) => constraint
+            .strip_prefix(table)
+            .and_then(|rest| rest.strip_prefix('_'))
+            .unwrap_or(constraint),
+        None => constraint,
+    };
+
+    ["_check", "_fkey", "_key", "_pkey"]
+        .iter()
+        .find_map(|suffix| without_table.strip_suffix(suffix))
+        .filter(|field| !field.is_empty())
+        .map(str::to_string)
+}
+
+/// `Json` extractor whose rejections are reported as [`AppError`] JSON bodies.
+#[derive(FromRequest)]
+#[from_request(via(axum::Json), rejection(AppError))]
+pub struct Json<T>(pub T);
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+/// `Query` extractor whose rejections are reported as [`AppError`] JSON bodies.
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(AppError))]
+pub struct Query<T>(pub T);
+
+/// `Path` extractor whose rejections are reported as [`AppError`] JSON bodies.
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Path), rejection(AppError))]
+pub struct Path<T>(pub T);
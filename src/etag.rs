@@ -0,0 +1,179 @@
+use axum::extract::FromRequestParts;
+use axum::http::header::{ETAG, IF_MATCH};
+use axum::http::request::Parts;
+use axum::http::{HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use sqlx::PgConnection;
+
+use crate::error::{AppError, Json};
+use crate::models::{
+    Album, AlbumDetail, Artist, ArtistDetail, Batch, BatchDetail, Entrega, EntregaDetail, LiveSet,
+    LiveSetEntry, Track, TrackDetail,
+};
+
+/// Records carrying the `version` column bumped on every UPDATE.
+pub trait Versioned {
+    fn version(&self) -> i32;
+}
+
+impl Versioned for Artist {
+    fn version(&self) -> i32 {
+        self.version
+    }
+}
+
+impl Versioned for Album {
+    fn version(&self) -> i32 {
+        self.version
+    }
+}
+
+impl Versioned for Track {
+    fn version(&self) -> i32 {
+        self.version
+    }
+}
+
+impl Versioned for Batch {
+    fn version(&self) -> i32 {
+        self.version
+    }
+}
+
+impl Versioned for Entrega {
+    fn version(&self) -> i32 {
+        self.version
+    }
+}
+
+impl Versioned for LiveSet {
+    fn version(&self) -> i32 {
+        self.version
+    }
+}
+
+impl Versioned for LiveSetEntry {
+    fn version(&self) -> i32 {
+        self.version
+    }
+}
+
+impl Versioned for ArtistDetail {
+    fn version(&self) -> i32 {
+        self.artist.version
+    }
+}
+
+impl Versioned for AlbumDetail {
+    fn version(&self) -> i32 {
+        self.album.version
+    }
+}
+
+impl Versioned for TrackDetail {
+    fn version(&self) -> i32 {
+        self.track.version
+    }
+}
+
+impl Versioned for EntregaDetail {
+    fn version(&self) -> i32 {
+        self.entrega.version
+    }
+}
+
+impl Versioned for BatchDetail {
+    fn version(&self) -> i32 {
+        self.batch.version
+    }
+}
+
+/// JSON body sent with the record's version as a strong `ETag`.
+pub struct Tagged<T>(pub T);
+
+impl<T: Versioned + Serialize> IntoResponse for Tagged<T> {
+    fn into_response(self) -> Response {
+        let etag = HeaderValue::from_str(&format!("\"{}\"", self.0.version()))
+            .expect("a quoted number is a valid header value");
+        ([(ETAG, etag)], Json(self.0)).into_response()
+    }
+}
+
+/// The `If-Match` header every PUT, PATCH and DELETE must send.
+/// Holds the accepted versions, or `None` for `*`.
+pub struct IfMatch(Option<Vec<i32>>);
+
+impl<S: Send + Sync> FromRequestParts<S> for IfMatch {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let header = parts.headers.get(IF_MATCH).ok_or_else(|| {
+            AppError::new(
+                StatusCode::PRECONDITION_REQUIRED,
+                "precondition_required",
+                "If-Match is required; send the ETag of the record as last read",
+            )
+            .with_field("If-Match")
+        })?;
+
+        let invalid = || {
+            AppError::bad_request("If-Match must be `*` or a list of ETags such as \"3\"")
+                .with_field("If-Match")
+        };
+        let value = header.to_str().map_err(|_| invalid())?.trim();
+        if value == "*" {
+            return Ok(Self(None));
+        }
+
+        // Weak tags never match under the strong comparison If-Match requires,
+        // and this API only hands out strong ones
+        value
+            .split(',')
+            .map(|tag| {
+                tag.trim()
+                    .strip_prefix('"')
+                    .and_then(|tag| tag.strip_suffix('"'))
+                    .and_then(|version| version.parse().ok())
+                    .ok_or_else(invalid)
+            })
+            .collect::<Result<Vec<i32>, AppError>>()
+            .map(|versions| Self(Some(versions)))
+    }
+}
+
+impl IfMatch {
+    /// Expect exactly this version, as carried by the hidden field of an admin form.
+    pub fn version(version: i32) -> Self {
+        Self(Some(vec![version]))
+    }
+
+    /// Lock the row and fail with 412 unless its version is one the client sent.
+    /// Must run inside the transaction that performs the write.
+    pub async fn check(
+        &self,
+        conn: &mut PgConnection,
+        table: &str,
+        resource: &str,
+        id: i64,
+    ) -> Result<(), AppError> {
+        let current: i32 = sqlx::query_scalar(&format!(
+            "SELECT version FROM {table} WHERE id = $1 FOR UPDATE"
+        ))
+        .bind(id)
+        .fetch_optional(conn)
+        .await?
+        .ok_or_else(|| AppError::not_found(resource, id))?;
+
+        match &self.0 {
+            Some(versions) if !versions.contains(&current) => Err(AppError::new(
+                StatusCode::PRECONDITION_FAILED,
+                "precondition_failed",
+                format!(
+                    "{resource} {id} was modified by someone else (now at version {current}); reload it and retry"
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
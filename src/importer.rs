@@ -0,0 +1,761 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sqlx::{Connection, PgConnection, Postgres, QueryBuilder, Transaction};
+
+use crate::artists;
+use crate::error::AppError;
+use crate::models::{
+    ImportItem, ImportParams, ImportPayload, NewAlbum, NewBatch, NewEntrega, NewTrack,
+};
+use crate::trash::Kind;
+use crate::validate::Validate;
+
+/// What to do with a payload record whose natural key is already in the database.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Existing {
+    #[default]
+    Skip,
+    Update,
+}
+
+#[derive(Debug)]
+enum Outcome {
+    Created,
+    Updated(Value), // The row as it was before, for the dry run diff
+    Skipped,
+}
+
+impl Outcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Outcome::Created => "created",
+            Outcome::Updated(_) => "updated",
+            Outcome::Skipped => "skipped",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportFailure {
+    pub index: usize, // Position in the payload list, from 0
+    pub message: String,
+}
+
+/// What a dry run would do to one payload record.
+#[derive(Debug, Serialize)]
+pub struct RecordDiff {
+    pub index: usize,
+    pub key: String,          // Natural key, e.g. the entrega name
+    pub action: &'static str, // created, updated, skipped or failed
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub changes: Map<String, Value>, // Column to {"from", "to"}
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct EntityReport {
+    pub created: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub failed: Vec<ImportFailure>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub records: Vec<RecordDiff>, // Only filled on dry runs
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub batches: EntityReport,
+    pub entregas: EntityReport,
+    pub albums: EntityReport,
+    pub tracks: EntityReport,
+}
+
+impl ImportReport {
+    /// Every failure as `list[index]: message`.
+    pub fn failures(&self) -> Vec<String> {
+        [
+            ("batches", &self.batches),
+            ("entregas", &self.entregas),
+            ("albums", &self.albums),
+            ("tracks", &self.tracks),
+        ]
+        .into_iter()
+        .flat_map(|(list, report)| {
+            report
+                .failed
+                .iter()
+                .map(move |failure| format!("{list}[{}]: {}", failure.index, failure.message))
+        })
+        .collect()
+    }
+}
+
+/// Database ids of the payload records, by position. `None` where the record failed.
+#[derive(Default)]
+struct Imported {
+    batches: Vec<Option<i64>>,
+    entregas: Vec<Option<i64>>,
+    albums: Vec<Option<i64>>,
+}
+
+/// Import a payload in dependency order: batches, entregas, albums, then tracks.
+///
+/// References are either 1-based positions in the payload's own lists
+/// (`"entrega_id": 1` is the first of `entregas`) or natural keys naming a record of
+/// the payload or the database (`"entrega": "Primera Entrega"`). Records already
+/// present are matched the same way: batches and entregas by name, albums by title
+/// and artist, tracks by title, artist and entrega. Only live records match, so a
+/// record that was trashed is imported anew, while records the payload itself marks
+/// as trashed (`deleted_at`, as in a snapshot) are skipped. With `existing=update` a
+/// matched record takes the values the payload gives; fields left out keep what is
+/// stored. Each record is written in its own savepoint, so one that fails is
+/// reported without undoing the others.
+pub async fn import(
+    conn: &mut PgConnection,
+    payload: &ImportPayload,
+    params: &ImportParams,
+) -> Result<ImportReport, sqlx::Error> {
+    let existing = params.existing;
+    let mut report = ImportReport {
+        dry_run: params.dry_run,
+        ..ImportReport::default()
+    };
+    let mut imported = Imported::default();
+
+    for (index, item) in payload.batches.iter().flatten().enumerate() {
+        let key = item.record.name.clone();
+        if item.deleted_at.is_some() {
+            report.batches.skip_trashed(index, key, params.dry_run);
+            imported.batches.push(None);
+            continue;
+        }
+        let mut savepoint = conn.begin().await?;
+        let result = import_batch(&mut savepoint, item).await;
+        let id = report
+            .batches
+            .settle(savepoint, Kind::Batch, index, key, result, params.dry_run)
+            .await?;
+        imported.batches.push(id);
+    }
+
+    for (index, item) in payload.entregas.iter().flatten().enumerate() {
+        let key = item.record.name.clone();
+        if item.deleted_at.is_some() {
+            report.entregas.skip_trashed(index, key, params.dry_run);
+            imported.entregas.push(None);
+            continue;
+        }
+        let mut savepoint = conn.begin().await?;
+        let result = import_entrega(&mut savepoint, item, existing, &imported).await;
+        let id = report
+            .entregas
+            .settle(savepoint, Kind::Entrega, index, key, result, params.dry_run)
+            .await?;
+        imported.entregas.push(id);
+    }
+
+    for (index, item) in payload.albums.iter().flatten().enumerate() {
+        let key = item.record.title.clone();
+        if item.deleted_at.is_some() {
+            report.albums.skip_trashed(index, key, params.dry_run);
+            imported.albums.push(None);
+            continue;
+        }
+        let mut savepoint = conn.begin().await?;
+        let result = import_album(&mut savepoint, item, existing).await;
+        let id = report
+            .albums
+            .settle(savepoint, Kind::Album, index, key, result, params.dry_run)
+            .await?;
+        imported.albums.push(id);
+    }
+
+    for (index, item) in payload.tracks.iter().flatten().enumerate() {
+        let key = format!("{} — {}", item.record.artist_name, item.record.title);
+        if item.deleted_at.is_some() {
+            report.tracks.skip_trashed(index, key, params.dry_run);
+            continue;
+        }
+        let mut savepoint = conn.begin().await?;
+        let result = import_track(&mut savepoint, item, existing, &imported).await;
+        report
+            .tracks
+            .settle(savepoint, Kind::Track, index, key, result, params.dry_run)
+            .await?;
+    }
+
+    Ok(report)
+}
+
+impl EntityReport {
+    /// Count a record the payload marks as trashed, as a snapshot does, as skipped:
+    /// importing it would bring it back live, and only `restore` refills the trash.
+    fn skip_trashed(&mut self, index: usize, key: String, diff: bool) {
+        self.skipped += 1;
+        if diff {
+            self.records.push(RecordDiff {
+                index,
+                key,
+                action: "skipped",
+                changes: Map::new(),
+                message: Some("The record is trashed in the payload".to_string()),
+            });
+        }
+    }
+
+    /// Keep or roll back one record's savepoint and count the outcome.
+    /// With `diff`, also note what changed, reading the row back before the
+    /// savepoint is released.
+    async fn settle(
+        &mut self,
+        mut savepoint: Transaction<'_, Postgres>,
+        kind: Kind,
+        index: usize,
+        key: String,
+        result: Result<(i64, Outcome), AppError>,
+        diff: bool,
+    ) -> Result<Option<i64>, sqlx::Error> {
+        match result {
+            Ok((id, outcome)) => {
+                let changes = match &outcome {
+                    Outcome::Created if diff => {
+                        changes(None, Some(&row(&mut savepoint, kind, id).await?))
+                    }
+                    Outcome::Updated(before) if diff => {
+                        changes(Some(before), Some(&row(&mut savepoint, kind, id).await?))
+                    }
+                    _ => Map::new(),
+                };
+                savepoint.commit().await?;
+
+                match outcome {
+                    Outcome::Created => self.created += 1,
+                    Outcome::Updated(_) => self.updated += 1,
+                    Outcome::Skipped => self.skipped += 1,
+                }
+                if diff {
+                    self.records.push(RecordDiff {
+                        index,
+                        key,
+                        action: outcome.as_str(),
+                        changes,
+                        message: None,
+                    });
+                }
+                Ok(Some(id))
+            }
+            Err(error) => {
+                savepoint.rollback().await?;
+                let message = if error.errors.len() > 1 {
+                    let errors: Vec<String> =
+                        error.errors.iter().map(ToString::to_string).collect();
+                    errors.join("; ")
+                } else {
+                    error.message
+                };
+
+                if diff {
+                    self.records.push(RecordDiff {
+                        index,
+                        key,
+                        action: "failed",
+                        changes: Map::new(),
+                        message: Some(message.clone()),
+                    });
+                }
+                self.failed.push(ImportFailure { index, message });
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// A row as JSON, shaped like the audit trigger records it.
+async fn row(conn: &mut PgConnection, kind: Kind, id: i64) -> Result<Value, sqlx::Error> {
+    sqlx::query_scalar(&format!(
+        "SELECT to_jsonb(t) - 'search_vector' FROM {} t WHERE id = $1",
+        kind.table()
+    ))
+    .bind(id)
+    .fetch_one(conn)
+    .await
+}
+
+/// Columns that differ between two versions of a row, as `{"from", "to"}`.
+/// Ids, timestamps and versions are bookkeeping and left out.
+fn changes(before: Option<&Value>, after: Option<&Value>) -> Map<String, Value> {
+    const IGNORED: [&str; 5] = ["id", "created_at", "updated_at", "version", "deleted_at"];
+    let empty = Map::new();
+    let before = before.and_then(Value::as_object).unwrap_or(&empty);
+    let after = after.and_then(Value::as_object).unwrap_or(&empty);
+
+    after
+        .iter()
+        .filter(|(column, _)| !IGNORED.contains(&column.as_str()))
+        .filter_map(|(column, value)| {
+            let old = before.get(column).unwrap_or(&Value::Null);
+            (old != value).then(|| (column.clone(), json!({ "from": old, "to": value })))
+        })
+        .collect()
+}
+
+/// Id of the live record a natural key names; trashed records stay in the trash.
+async fn find_by_key(
+    conn: &mut PgConnection,
+    kind: Kind,
+    key: &str,
+) -> Result<Option<i64>, sqlx::Error> {
+    sqlx::query_scalar(&format!(
+        "SELECT id FROM {} WHERE {} = $1 AND deleted_at IS NULL ORDER BY id LIMIT 1",
+        kind.table(),
+        kind.label_column()
+    ))
+    .bind(key)
+    .fetch_optional(conn)
+    .await
+}
+
+/// Id of the live album with this title by this artist, or without one for `None`.
+async fn find_album(
+    conn: &mut PgConnection,
+    title: &str,
+    artist_id: Option<i64>,
+) -> Result<Option<i64>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT id FROM albums
+         WHERE title = $1 AND artist_id IS NOT DISTINCT FROM $2 AND deleted_at IS NULL
+         ORDER BY id LIMIT 1",
+    )
+    .bind(title)
+    .bind(artist_id)
+    .fetch_optional(conn)
+    .await
+}
+
+/// Resolve a track's `album` by title. When several live albums share the title, the
+/// one by `artist` (the track's `album_artist`, else its own artist) is taken; a lone
+/// album matches whatever its artist, so compilations need no `album_artist`.
+async fn album_reference(
+    conn: &mut PgConnection,
+    title: &str,
+    album_artist: Option<&str>,
+    artist: &str,
+) -> Result<i64, AppError> {
+    let albums: Vec<(i64, Option<String>)> = sqlx::query_as(
+        "SELECT al.id, ar.name FROM albums al LEFT JOIN artists ar ON ar.id = al.artist_id
+         WHERE al.title = $1 AND al.deleted_at IS NULL ORDER BY al.id",
+    )
+    .bind(title)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let by_artist = |name: &str| {
+        albums
+            .iter()
+            .find(|(_, album_artist)| album_artist.as_deref() == Some(name))
+            .map(|(id, _)| *id)
+    };
+    let found = match (albums.as_slice(), album_artist) {
+        ([], _) => None,
+        ([(id, _)], None) => Some(*id),
+        (_, Some(name)) => by_artist(name),
+        (_, None) => by_artist(artist),
+    };
+
+    found.ok_or_else(|| {
+        let message = match (albums.len(), album_artist) {
+            (0, _) => format!("No album is named `{title}` in the payload or the database"),
+            (_, Some(name)) => format!("No album named `{title}` is by {name}"),
+            (count, None) => format!(
+                "{count} albums are named `{title}` and none is by {artist}; name its artist with `album_artist`"
+            ),
+        };
+        AppError::unprocessable(message).with_field("album")
+    })
+}
+
+/// Resolve a reference given by natural key or by 1-based payload position.
+async fn reference(
+    conn: &mut PgConnection,
+    kind: Kind,
+    key: Option<&str>,
+    position: Option<i64>,
+    imported: &[Option<i64>],
+) -> Result<Option<i64>, AppError> {
+    let field = kind.as_str();
+    if let Some(key) = key {
+        return find_by_key(conn, kind, key)
+            .await?
+            .map(Some)
+            .ok_or_else(|| {
+                AppError::unprocessable(format!(
+                    "No {field} is named `{key}` in the payload or the database"
+                ))
+                .with_field(field)
+            });
+    }
+
+    let Some(position) = position else {
+        return Ok(None);
+    };
+    let list = kind.table();
+    match usize::try_from(position - 1).ok().and_then(|index| imported.get(index)) {
+        Some(Some(id)) => Ok(Some(*id)),
+        Some(None) => Err(AppError::unprocessable(format!(
+            "{list}[{}] was not imported, so nothing can reference it",
+            position - 1
+        ))
+        .with_field(format!("{field}_id"))),
+        None => Err(AppError::unprocessable(format!(
+            "{field}_id {position} is not a position in the payload's {list}; name an existing {field} with `{field}` instead"
+        ))
+        .with_field(format!("{field}_id"))),
+    }
+}
+
+async fn import_batch(
+    conn: &mut PgConnection,
+    item: &ImportItem<NewBatch>,
+) -> Result<(i64, Outcome), AppError> {
+    let batch = &item.record;
+    batch.validate()?;
+
+    // The name is all a batch has, so there is nothing to update
+    if let Some(id) = find_by_key(conn, Kind::Batch, &batch.name).await? {
+        return Ok((id, Outcome::Skipped));
+    }
+
+    let id = sqlx::query_scalar(
+        "INSERT INTO batches (name, created_at) VALUES ($1, COALESCE($2, now())) RETURNING id",
+    )
+    .bind(&batch.name)
+    .bind(batch.created_at)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok((id, Outcome::Created))
+}
+
+async fn import_entrega(
+    conn: &mut PgConnection,
+    item: &ImportItem<NewEntrega>,
+    existing: Existing,
+    imported: &Imported,
+) -> Result<(i64, Outcome), AppError> {
+    let entrega = &item.record;
+    entrega.validate()?;
+    let batch_id = reference(
+        conn,
+        Kind::Batch,
+        item.refs.batch.as_deref(),
+        entrega.batch_id,
+        &imported.batches,
+    )
+    .await?;
+
+    match (
+        find_by_key(conn, Kind::Entrega, &entrega.name).await?,
+        existing,
+    ) {
+        (Some(id), Existing::Skip) => Ok((id, Outcome::Skipped)),
+        (Some(id), Existing::Update) => {
+            let outcome = PayloadUpdate::new()
+                .set("batch_id", batch_id)
+                .apply(conn, Kind::Entrega, id)
+                .await?;
+            Ok((id, outcome))
+        }
+        (None, _) => {
+            let id = sqlx::query_scalar(
+                "INSERT INTO entregas (name, batch_id, created_at) VALUES ($1, $2, COALESCE($3, now())) RETURNING id",
+            )
+            .bind(&entrega.name)
+            .bind(batch_id)
+            .bind(entrega.created_at)
+            .fetch_one(&mut *conn)
+            .await?;
+            Ok((id, Outcome::Created))
+        }
+    }
+}
+
+async fn import_album(
+    conn: &mut PgConnection,
+    item: &ImportItem<NewAlbum>,
+    existing: Existing,
+) -> Result<(i64, Outcome), AppError> {
+    let album = &item.record;
+    album.validate()?;
+    let artist_id = match item.refs.artist.as_deref() {
+        Some(name) => Some(artists::find_or_create_artist(conn, name).await?),
+        None => album.artist_id,
+    };
+
+    match (find_album(conn, &album.title, artist_id).await?, existing) {
+        (Some(id), Existing::Skip) => Ok((id, Outcome::Skipped)),
+        // The artist is part of the match, so it never changes here
+        (Some(id), Existing::Update) => {
+            let outcome = PayloadUpdate::new()
+                .set("release_year", album.release_year)
+                .set("label", album.label.clone())
+                .set("format", album.format.clone())
+                .set("country", album.country.clone())
+                .set("genre", album.genre.clone())
+                .set("style", album.style.clone())
+                .apply(conn, Kind::Album, id)
+                .await?;
+            Ok((id, outcome))
+        }
+        (None, _) => {
+            let id = sqlx::query_scalar(
+                "INSERT INTO albums (title, artist_id, release_year, label, format, country, genre, style, created_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, COALESCE($9, now())) RETURNING id",
+            )
+            .bind(&album.title)
+            .bind(artist_id)
+            .bind(album.release_year)
+            .bind(&album.label)
+            .bind(&album.format)
+            .bind(&album.country)
+            .bind(&album.genre)
+            .bind(&album.style)
+            .bind(album.created_at)
+            .fetch_one(&mut *conn)
+            .await?;
+            Ok((id, Outcome::Created))
+        }
+    }
+}
+
+async fn import_track(
+    conn: &mut PgConnection,
+    item: &ImportItem<NewTrack>,
+    existing: Existing,
+    imported: &Imported,
+) -> Result<(i64, Outcome), AppError> {
+    let track = &item.record;
+    track.validate()?;
+    let album_id = match item.refs.album.as_deref() {
+        Some(title) => Some(
+            album_reference(
+                conn,
+                title,
+                item.refs.album_artist.as_deref(),
+                &track.artist_name,
+            )
+            .await?,
+        ),
+        None => reference(conn, Kind::Album, None, track.album_id, &imported.albums).await?,
+    };
+    let entrega_id = reference(
+        conn,
+        Kind::Entrega,
+        item.refs.entrega.as_deref(),
+        track.entrega_id,
+        &imported.entregas,
+    )
+    .await?;
+
+    let found: Option<i64> = sqlx::query_scalar(
+        "SELECT id FROM tracks
+         WHERE title = $1 AND artist_name = $2 AND entrega_id IS NOT DISTINCT FROM $3 AND deleted_at IS NULL
+         ORDER BY id LIMIT 1",
+    )
+    .bind(&track.title)
+    .bind(&track.artist_name)
+    .bind(entrega_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    match (found, existing) {
+        (Some(id), Existing::Skip) => Ok((id, Outcome::Skipped)),
+        (Some(id), Existing::Update) => {
+            let outcome = PayloadUpdate::new()
+                .set("album_id", album_id)
+                .set("duration_seconds", track.duration_seconds)
+                .set("bpm", track.bpm)
+                .set("tone", track.tone.clone())
+                .set("position", track.position.clone())
+                .set("score", track.score.clone())
+                .apply(conn, Kind::Track, id)
+                .await?;
+            Ok((id, outcome))
+        }
+        (None, _) => {
+            let id = sqlx::query_scalar(
+                "INSERT INTO tracks (title, artist_name, album_id, duration_seconds, bpm, tone, position, score, entrega_id, created_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, COALESCE($10, now())) RETURNING id",
+            )
+            .bind(&track.title)
+            .bind(&track.artist_name)
+            .bind(album_id)
+            .bind(track.duration_seconds)
+            .bind(track.bpm)
+            .bind(&track.tone)
+            .bind(&track.position)
+            .bind(&track.score)
+            .bind(entrega_id)
+            .bind(track.created_at)
+            .fetch_one(&mut *conn)
+            .await?;
+
+            artists::sync_main_artist(conn, id, &track.artist_name).await?;
+            Ok((id, Outcome::Created))
+        }
+    }
+}
+
+/// `UPDATE` of a record the payload matched, setting only the columns the payload
+/// record gives a value for: fields it leaves out, or sets to null, keep what is
+/// stored. The row is only written when one of the given values differs.
+struct PayloadUpdate {
+    query: QueryBuilder<'static, Postgres>,
+    columns: Vec<&'static str>,
+}
+
+impl PayloadUpdate {
+    fn new() -> Self {
+        Self {
+            query: QueryBuilder::new("WITH payload AS (SELECT "),
+            columns: Vec::new(),
+        }
+    }
+
+    fn set<T>(mut self, column: &'static str, value: Option<T>) -> Self
+    where
+        T: 'static + sqlx::Encode<'static, Postgres> + sqlx::Type<Postgres> + Send,
+    {
+        if let Some(value) = value {
+            if !self.columns.is_empty() {
+                self.query.push(", ");
+            }
+            self.query.push_bind(value).push(format!(" AS {column}"));
+            self.columns.push(column);
+        }
+        self
+    }
+
+    /// The statement, or `None` when the record gives nothing to set.
+    fn finish(mut self, table: &str, id: i64) -> Option<QueryBuilder<'static, Postgres>> {
+        if self.columns.is_empty() {
+            return None;
+        }
+
+        let list = |prefix: &str| -> Vec<String> {
+            self.columns
+                .iter()
+                .map(|column| format!("{prefix}{column}"))
+                .collect()
+        };
+        let set: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| format!("{column} = payload.{column}"))
+            .collect();
+        self.query
+            .push(format!(
+                ") UPDATE {table} t SET {}, updated_at = now() FROM payload WHERE t.id = ",
+                set.join(", ")
+            ))
+            .push_bind(id)
+            .push(format!(
+                " AND ROW({}) IS DISTINCT FROM ROW({})",
+                list("t.").join(", "),
+                list("payload.").join(", ")
+            ));
+
+        Some(self.query)
+    }
+
+    /// Run the update, telling whether the row changed.
+    async fn apply(
+        self,
+        conn: &mut PgConnection,
+        kind: Kind,
+        id: i64,
+    ) -> Result<Outcome, sqlx::Error> {
+        let Some(mut query) = self.finish(kind.table(), id) else {
+            return Ok(Outcome::Skipped);
+        };
+        let before = row(conn, kind, id).await?;
+        let result = query.build().execute(&mut *conn).await?;
+
+        Ok(if result.rows_affected() > 0 {
+            Outcome::Updated(before)
+        } else {
+            Outcome::Skipped
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn created_rows_list_every_column_set() {
+        let after =
+            json!({ "id": 4, "title": "Neighbors", "label": "Strictly Rhythm", "genre": null });
+        let diff = changes(None, Some(&after));
+
+        assert_eq!(
+            Value::Object(diff),
+            json!({
+                "title": { "from": null, "to": "Neighbors" },
+                "label": { "from": null, "to": "Strictly Rhythm" }
+            })
+        );
+    }
+
+    #[test]
+    fn updated_rows_list_only_what_changed() {
+        let before = json!({
+            "id": 4, "title": "Neighbors", "label": "Strictly Rhythm", "release_year": 1995,
+            "version": 2, "updated_at": "2026-03-01T00:00:00Z"
+        });
+        let after = json!({
+            "id": 4, "title": "Neighbors", "label": "Nervous", "release_year": null,
+            "version": 3, "updated_at": "2026-03-02T00:00:00Z"
+        });
+        let diff = changes(Some(&before), Some(&after));
+
+        assert_eq!(
+            Value::Object(diff),
+            json!({
+                "label": { "from": "Strictly Rhythm", "to": "Nervous" },
+                "release_year": { "from": 1995, "to": null }
+            })
+        );
+    }
+
+    #[test]
+    fn updates_set_only_the_fields_given() {
+        let query = PayloadUpdate::new()
+            .set("release_year", None::<i64>)
+            .set("label", Some("Nervous".to_string()))
+            .set("genre", None::<String>)
+            .finish("albums", 4)
+            .unwrap();
+
+        assert_eq!(
+            query.sql(),
+            "WITH payload AS (SELECT $1 AS label) \
+             UPDATE albums t SET label = payload.label, updated_at = now() FROM payload \
+             WHERE t.id = $2 AND ROW(t.label) IS DISTINCT FROM ROW(payload.label)"
+        );
+        assert!(PayloadUpdate::new()
+            .set("genre", None::<String>)
+            .finish("albums", 4)
+            .is_none());
+    }
+
+    #[test]
+    fn bookkeeping_columns_are_left_out() {
+        let before = json!({ "id": 1, "created_at": "a", "deleted_at": null, "version": 1 });
+        let after = json!({ "id": 2, "created_at": "b", "deleted_at": "c", "version": 2 });
+        assert!(changes(Some(&before), Some(&after)).is_empty());
+    }
+}
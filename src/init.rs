@@ -1,180 +1,201 @@
-use sqlx::PgPool;
-use std::collections::HashMap;
-
-/// Initialize database with initial data from init_data.json
-/// This function is granular and idempotent - checks each item individually
-/// Only adds entregas, albums, and tracks that don't already exist
-pub async fn init_data(pool: &PgPool) -> Result<(), Box<dyn std::error::Error>> {
-    println!("🔧 Checking initial data...");
-
-    // Read init_data.json
-    let init_file = std::fs::read_to_string("init_data.json")?;
-    let init_payload: crate::models::ImportPayload = serde_json::from_str(&init_file)?;
-
-    let mut tx = pool.begin().await?;
-    let mut new_entregas_count = 0;
-    let mut new_albums_count = 0;
-    let mut new_tracks_count = 0;
-    
-    // Map JSON entrega positions (1, 2, 3...) to actual database IDs
-    let mut json_entrega_pos_to_db_id: HashMap<i64, i64> = HashMap::new();
-    
-    // Map JSON album positions (1, 2, 3...) to actual database IDs
-    let mut json_album_pos_to_db_id: HashMap<i64, i64> = HashMap::new();
-    
-    // Get all existing entregas to build the name->id map
-    let mut entrega_name_to_id: HashMap<String, i64> = HashMap::new();
-    let existing_entregas: Vec<(i64, String)> =
-        sqlx::query_as("SELECT id, name FROM entregas")
-            .fetch_all(&mut *tx)
-            .await?;
-
-    for (id, name) in existing_entregas {
-        entrega_name_to_id.insert(name, id);
-    }
-    
-    // Get all existing albums to build the title->id map
-    let mut album_title_to_id: HashMap<String, i64> = HashMap::new();
-    let existing_albums: Vec<(i64, String)> =
-        sqlx::query_as("SELECT id, title FROM albums")
-            .fetch_all(&mut *tx)
-            .await?;
-
-    for (id, title) in existing_albums {
-        album_title_to_id.insert(title, id);
-    }
+use std::path::{Path, PathBuf};
 
-    // Process entregas from JSON
-    if let Some(entregas) = &init_payload.entregas {
-        for (json_index, entrega) in entregas.iter().enumerate() {
-            let json_position = (json_index + 1) as i64; // JSON uses 1-based indexing
-            
-            // Check if this entrega already exists
-            if let Some(&existing_id) = entrega_name_to_id.get(&entrega.name) {
-                println!("  ⏭️  Entrega '{}' already exists, skipping", entrega.name);
-                json_entrega_pos_to_db_id.insert(json_position, existing_id);
-                continue;
-            }
+use sha2::{Digest, Sha256};
+use sqlx::{Connection, PgPool};
 
-            // Create the entrega
-            let entrega_id: i64 = sqlx::query_scalar(
-                "INSERT INTO entregas (name, batch_id, created_at) VALUES ($1, $2, $3) RETURNING id",
-            )
-            .bind(&entrega.name)
-            .bind(entrega.batch_id)
-            .bind(&entrega.created_at)
-            .fetch_one(&mut *tx)
-            .await?;
-
-            json_entrega_pos_to_db_id.insert(json_position, entrega_id);
-            entrega_name_to_id.insert(entrega.name.clone(), entrega_id);
-            new_entregas_count += 1;
-            println!("  ✅ Created entrega: {}", entrega.name);
+use crate::audit;
+use crate::importer::{self, EntityReport, Existing, ImportReport};
+use crate::models::{ImportParams, ImportPayload};
+use crate::validate::Validate;
+
+/// Seed file, or directory of seed files, used when `SEED_PATH` is not set.
+const DEFAULT_SEED_PATH: &str = "init_data.json";
+
+/// Where the seed data lives: `SEED_PATH`, relative to the working directory.
+pub fn seed_path() -> PathBuf {
+    std::env::var("SEED_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(DEFAULT_SEED_PATH))
+}
+
+/// Whether to seed on every start; `SEED_ON_START=false` leaves it to the `seed` command.
+pub fn seed_on_start() -> bool {
+    std::env::var("SEED_ON_START").map_or(true, |value| {
+        !matches!(
+            value.trim().to_lowercase().as_str(),
+            "false" | "0" | "no" | "off"
+        )
+    })
+}
+
+/// Initialize database with the seed data at [`seed_path`]
+/// The path is one JSON file or a directory of them, applied in file name order
+/// Each file is recorded in seed_files and applied again only once it changes;
+/// a new file only adds the batches, entregas, albums and tracks that don't
+/// already exist, a changed one also updates the existing ones with the fields
+/// it gives
+/// With `dry_run` nothing is written; what would change is printed instead
+pub async fn init_data(pool: &PgPool, dry_run: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let path = seed_path();
+    println!("🔧 Checking initial data in {}...", path.display());
+
+    // A dry run keeps every file in one transaction rolled back at the end, so later
+    // files can name what earlier ones would add
+    let mut dry_run_tx = if dry_run {
+        Some(audit::begin(pool, "init").await?)
+    } else {
+        None
+    };
+    let mut applied = 0;
+    for file in seed_files(&path)? {
+        let name = file
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| file.display().to_string());
+        let contents = std::fs::read(&file)?;
+        let checksum = hex::encode(Sha256::digest(&contents));
+
+        let recorded: Option<String> =
+            sqlx::query_scalar("SELECT checksum FROM seed_files WHERE name = $1")
+                .bind(&name)
+                .fetch_optional(pool)
+                .await?;
+        if recorded.as_deref() == Some(checksum.as_str()) {
+            continue;
         }
-    }
 
-    // Process albums from JSON
-    if let Some(albums) = &init_payload.albums {
-        for (json_index, album) in albums.iter().enumerate() {
-            let json_position = (json_index + 1) as i64; // JSON uses 1-based indexing
-            
-            // Check if this album already exists
-            if let Some(&existing_id) = album_title_to_id.get(&album.title) {
-                println!("  ⏭️  Album '{}' already exists, skipping", album.title);
-                json_album_pos_to_db_id.insert(json_position, existing_id);
-                continue;
-            }
+        let payload: ImportPayload = serde_json::from_slice(&contents)
+            .map_err(|error| format!("{name} is not a valid seed file: {error}"))?;
 
-            // Create the album
-            let album_id: i64 = sqlx::query_scalar(
-                "INSERT INTO albums (title, artist_id, release_year, label, format, country, genre, style, created_at) 
-                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING id",
-            )
-            .bind(&album.title)
-            .bind(album.artist_id)
-            .bind(album.release_year)
-            .bind(&album.label)
-            .bind(&album.format)
-            .bind(&album.country)
-            .bind(&album.genre)
-            .bind(&album.style)
-            .bind(&album.created_at)
-            .fetch_one(&mut *tx)
-            .await?;
-
-            json_album_pos_to_db_id.insert(json_position, album_id);
-            album_title_to_id.insert(album.title.clone(), album_id);
-            new_albums_count += 1;
-            println!("  ✅ Created album: {}", album.title);
+        // Refuse to seed records the API would reject
+        if let Err(errors) = payload.validate() {
+            let details: Vec<String> = errors.iter().map(ToString::to_string).collect();
+            return Err(format!("{name} is invalid:\n  {}", details.join("\n  ")).into());
         }
-    }
 
-    // Get all existing tracks to detect duplicates
-    let existing_tracks: Vec<(String, String, Option<i64>)> =
-        sqlx::query_as("SELECT title, artist_name, entrega_id FROM tracks")
-            .fetch_all(&mut *tx)
-            .await?;
-    
-    let mut existing_tracks_set = std::collections::HashSet::new();
-    for (title, artist, entrega_id) in existing_tracks {
-        existing_tracks_set.insert((title, artist, entrega_id));
-    }
+        // Same rules as POST /import/json: positional or named references; records
+        // already there are left alone unless the file changed since it was applied
+        let mut tx = match dry_run_tx.as_mut() {
+            Some(outer) => outer.begin().await?,
+            None => audit::begin(pool, "init").await?,
+        };
+        let params = ImportParams {
+            existing: match recorded {
+                Some(_) => Existing::Update,
+                None => Existing::Skip,
+            },
+            dry_run,
+        };
+        let report = importer::import(&mut tx, &payload, &params).await?;
+
+        if dry_run {
+            tx.commit().await?;
+            print_diff(&name, &report);
+            applied += 1;
+            continue;
+        }
 
-    // Process tracks from JSON
-    if let Some(tracks) = &init_payload.tracks {
-        for track in tracks {
-            // Map the JSON entrega_id to actual database ID
-            let actual_entrega_id = track.entrega_id.and_then(|json_id| {
-                json_entrega_pos_to_db_id.get(&json_id).copied()
-            });
-            
-            // Map the JSON album_id to actual database ID
-            let actual_album_id = track.album_id.and_then(|json_id| {
-                json_album_pos_to_db_id.get(&json_id).copied()
-            });
-            
-            // Check if this track already exists
-            let track_key = (track.title.clone(), track.artist_name.clone(), actual_entrega_id);
-            if existing_tracks_set.contains(&track_key) {
-                continue; // Skip existing track silently
-            }
-            
-            sqlx::query(
-                "INSERT INTO tracks (title, artist_name, album_id, duration_seconds, bpm, tone, position, score, entrega_id, created_at) 
-                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)"
-            )
-            .bind(&track.title)
-            .bind(&track.artist_name)
-            .bind(actual_album_id)
-            .bind(track.duration_seconds)
-            .bind(track.bpm)
-            .bind(&track.tone)
-            .bind(&track.position)
-            .bind(&track.score)
-            .bind(actual_entrega_id)
-            .bind(&track.created_at)
-            .execute(&mut *tx)
-            .await?;
-
-            new_tracks_count += 1;
-            existing_tracks_set.insert(track_key);
+        let failures = report.failures();
+        if !failures.is_empty() {
+            return Err(format!("{name} could not be loaded:\n  {}", failures.join("\n  ")).into());
         }
 
-        if new_tracks_count > 0 {
-            println!("     → Added {} tracks", new_tracks_count);
+        sqlx::query(
+            "INSERT INTO seed_files (name, checksum) VALUES ($1, $2)
+             ON CONFLICT (name) DO UPDATE SET checksum = EXCLUDED.checksum, applied_at = now()",
+        )
+        .bind(&name)
+        .bind(&checksum)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        applied += 1;
+
+        println!("\n✅ Applied {name}");
+        let written = [
+            ("batch(es)", report.batches.created, report.batches.updated),
+            ("entrega(s)", report.entregas.created, report.entregas.updated),
+            ("album(s)", report.albums.created, report.albums.updated),
+            ("track(s)", report.tracks.created, report.tracks.updated),
+        ];
+        if written.iter().all(|(_, created, updated)| created + updated == 0) {
+            println!("   → everything in it already exists");
+        }
+        for (what, created, updated) in written {
+            if created > 0 {
+                println!("   → {} {} created", created, what);
+            }
+            if updated > 0 {
+                println!("   → {} {} updated", updated, what);
+            }
         }
     }
 
-    tx.commit().await?;
-
-    if new_entregas_count > 0 || new_albums_count > 0 || new_tracks_count > 0 {
-        println!("\n✅ Initial data initialized successfully!");
-        println!("   → {} entrega(s) created", new_entregas_count);
-        println!("   → {} album(s) created", new_albums_count);
-        println!("   → {} track(s) created", new_tracks_count);
-    } else {
-        println!("\n⏭️  All initial data already exists, nothing to add");
+    if let Some(tx) = dry_run_tx {
+        tx.rollback().await?;
+    }
+    if applied == 0 {
+        println!("\n⏭️  All seed files already applied, nothing to add");
     }
 
     Ok(())
 }
+
+/// The seed file at `path`, or the `.json` files of a directory sorted by name.
+fn seed_files(path: &Path) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    if !path.is_dir() {
+        if !path.is_file() {
+            return Err(format!(
+                "No seed data at {} (set SEED_PATH, or SEED_ON_START=false to skip seeding)",
+                path.display()
+            )
+            .into());
+        }
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files: Vec<PathBuf> = std::fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    files.retain(|file| file.is_file() && file.extension().is_some_and(|ext| ext == "json"));
+    files.sort();
+    Ok(files)
+}
+
+fn print_diff(name: &str, report: &ImportReport) {
+    println!("\n🔍 Dry run of {name}, nothing was written:");
+    let lists: [(&str, &EntityReport); 4] = [
+        ("batches", &report.batches),
+        ("entregas", &report.entregas),
+        ("albums", &report.albums),
+        ("tracks", &report.tracks),
+    ];
+    for (list, entity) in lists {
+        for record in &entity.records {
+            let mark = match record.action {
+                "created" => "+",
+                "updated" => "~",
+                "failed" => "✗",
+                _ => "=",
+            };
+            println!(
+                "  {} {}[{}] {} ({})",
+                mark, list, record.index, record.key, record.action
+            );
+            for (column, change) in &record.changes {
+                println!("      {}: {} → {}", column, change["from"], change["to"]);
+            }
+            if let Some(message) = &record.message {
+                println!("      {}", message);
+            }
+        }
+        println!(
+            "   → {}: {} to create, {} to update, {} unchanged, {} failing",
+            list,
+            entity.created,
+            entity.updated,
+            entity.skipped,
+            entity.failed.len()
+        );
+    }
+}
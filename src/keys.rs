@@ -0,0 +1,211 @@
+/// Every tone accepted by the `tracks_tone_check` constraint
+/// (see migration `20260227000300_add_tone_constraint.sql`).
+pub const TONES: [&str; 42] = [
+    "C", "C#", "Db", "D", "D#", "Eb", "E", "E#", "Fb", "F", "F#", "Gb", "G", "G#", "Ab", "A", "A#",
+    "Bb", "B", "B#", "Cb", "Cm", "C#m", "Dbm", "Dm", "D#m", "Ebm", "Em", "E#m", "Fbm", "Fm", "F#m",
+    "Gbm", "Gm", "G#m", "Abm", "Am", "A#m", "Bbm", "Bm", "B#m", "Cbm",
+];
+
+/// A musical key reduced to its pitch class, so enharmonic spellings
+/// (`Db`/`C#`, `Fbm`/`Em`, `B#`/`C`) compare equal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    pitch_class: u8, // 0 = C, 1 = C#/Db, ... 11 = B
+    minor: bool,
+}
+
+/// How two keys relate on the Camelot wheel, from smoothest to roughest mix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Relation {
+    SameKey,
+    Relative,
+    Adjacent,
+}
+
+impl Relation {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Relation::SameKey => "same_key",
+            Relation::Relative => "relative",
+            Relation::Adjacent => "adjacent",
+        }
+    }
+}
+
+impl Key {
+    /// Parse one of the [`TONES`] spellings.
+    pub fn parse(tone: &str) -> Option<Self> {
+        if !TONES.contains(&tone) {
+            return None;
+        }
+
+        let (root, minor) = match tone.strip_suffix('m') {
+            Some(root) => (root, true),
+            None => (tone, false),
+        };
+        let mut chars = root.chars();
+        let natural: i8 = match chars.next()? {
+            'C' => 0,
+            'D' => 2,
+            'E' => 4,
+            'F' => 5,
+            'G' => 7,
+            'A' => 9,
+            'B' => 11,
+            _ => return None,
+        };
+        let accidental: i8 = match chars.next() {
+            Some('#') => 1,
+            Some('b') => -1,
+            None => 0,
+            _ => return None,
+        };
+
+        Some(Self {
+            pitch_class: (natural + accidental).rem_euclid(12) as u8,
+            minor,
+        })
+    }
+
+    /// Position on the Camelot wheel (1-12). C major is 8B and A minor is 8A.
+    pub fn camelot_number(self) -> u8 {
+        // Minor keys share the number of their relative major (three semitones up)
+        let major_pitch_class = if self.minor {
+            (self.pitch_class + 3) % 12
+        } else {
+            self.pitch_class
+        };
+        // Each step clockwise on the wheel is a fifth (7 semitones)
+        (7 * major_pitch_class + 7) % 12 + 1
+    }
+
+    /// Camelot notation, e.g. `3B` for Db major or `4A` for F minor.
+    pub fn camelot(self) -> String {
+        format!(
+            "{}{}",
+            self.camelot_number(),
+            if self.minor { 'A' } else { 'B' }
+        )
+    }
+
+    /// Open Key notation, e.g. `8d` for Db major or `9m` for F minor.
+    pub fn open_key(self) -> String {
+        let number = (self.camelot_number() + 4) % 12 + 1;
+        format!("{}{}", number, if self.minor { 'm' } else { 'd' })
+    }
+
+    /// Steps between two keys: wheel distance plus one when switching between major and minor.
+    pub fn distance(self, other: Key) -> u8 {
+        let diff = self.camelot_number().abs_diff(other.camelot_number());
+        diff.min(12 - diff) + u8::from(self.minor != other.minor)
+    }
+
+    /// Harmonic relation for a smooth mix, or `None` when the keys clash.
+    pub fn relation(self, other: Key) -> Option<Relation> {
+        let diff = self.camelot_number().abs_diff(other.camelot_number());
+        let wheel_distance = diff.min(12 - diff);
+
+        match (wheel_distance, self.minor == other.minor) {
+            (0, true) => Some(Relation::SameKey),
+            (0, false) => Some(Relation::Relative),
+            (1, true) => Some(Relation::Adjacent),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(tone: &str) -> Key {
+        Key::parse(tone).unwrap_or_else(|| panic!("{tone} should parse"))
+    }
+
+    #[test]
+    fn parses_every_tone_and_nothing_else() {
+        for tone in TONES {
+            assert!(Key::parse(tone).is_some(), "{tone}");
+        }
+        for tone in ["", "H", "c", "C##", "Cmaj", "Bbb", "m"] {
+            assert_eq!(Key::parse(tone), None, "{tone}");
+        }
+    }
+
+    #[test]
+    fn enharmonic_spellings_are_the_same_key() {
+        assert_eq!(key("Db"), key("C#"));
+        assert_eq!(key("B#"), key("C"));
+        assert_eq!(key("Cb"), key("B"));
+        assert_eq!(key("Fbm"), key("Em"));
+        assert_eq!(key("E#m"), key("Fm"));
+        assert_ne!(key("C"), key("Cm"));
+    }
+
+    #[test]
+    fn camelot_wheel() {
+        let majors = [
+            ("B", "1B"),
+            ("F#", "2B"),
+            ("Db", "3B"),
+            ("Ab", "4B"),
+            ("Eb", "5B"),
+            ("Bb", "6B"),
+            ("F", "7B"),
+            ("C", "8B"),
+            ("G", "9B"),
+            ("D", "10B"),
+            ("A", "11B"),
+            ("E", "12B"),
+        ];
+        let minors = [
+            ("G#m", "1A"),
+            ("D#m", "2A"),
+            ("Bbm", "3A"),
+            ("Fm", "4A"),
+            ("Cm", "5A"),
+            ("Gm", "6A"),
+            ("Dm", "7A"),
+            ("Am", "8A"),
+            ("Em", "9A"),
+            ("Bm", "10A"),
+            ("F#m", "11A"),
+            ("C#m", "12A"),
+        ];
+        for (tone, camelot) in majors.into_iter().chain(minors) {
+            assert_eq!(key(tone).camelot(), camelot, "{tone}");
+        }
+    }
+
+    #[test]
+    fn open_key_notation() {
+        assert_eq!(key("C").open_key(), "1d");
+        assert_eq!(key("Am").open_key(), "1m");
+        assert_eq!(key("Db").open_key(), "8d");
+        assert_eq!(key("Fm").open_key(), "9m");
+        assert_eq!(key("F").open_key(), "12d");
+    }
+
+    #[test]
+    fn relations_on_the_wheel() {
+        assert_eq!(key("C").relation(key("C")), Some(Relation::SameKey));
+        assert_eq!(key("C").relation(key("Am")), Some(Relation::Relative));
+        assert_eq!(key("C").relation(key("G")), Some(Relation::Adjacent));
+        assert_eq!(key("Am").relation(key("Dm")), Some(Relation::Adjacent));
+        // 12B and 1B are neighbours across the top of the wheel
+        assert_eq!(key("E").relation(key("B")), Some(Relation::Adjacent));
+        assert_eq!(key("C").relation(key("D")), None);
+        assert_eq!(key("C").relation(key("Cm")), None);
+        assert_eq!(key("C").relation(key("Em")), None);
+    }
+
+    #[test]
+    fn distance_counts_wheel_steps_and_mode_changes() {
+        assert_eq!(key("C").distance(key("C")), 0);
+        assert_eq!(key("C").distance(key("Am")), 1);
+        assert_eq!(key("C").distance(key("D")), 2);
+        assert_eq!(key("E").distance(key("F#")), 2);
+        assert_eq!(key("C").distance(key("F#")), 6);
+        assert_eq!(key("C").distance(key("Cm")), 4);
+    }
+}
@@ -0,0 +1,694 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{Postgres, QueryBuilder};
+
+use crate::error::AppError;
+use crate::models::{
+    AlbumFilter, ArtistFilter, BatchFilter, EntregaFilter, LiveSetFilter, TrackFilter,
+};
+
+/// Default and maximum page sizes for list endpoints.
+pub const DEFAULT_LIMIT: i64 = 50;
+pub const MAX_LIMIT: i64 = 500;
+
+/// Envelope returned by every list endpoint.
+///
+/// `next_cursor` is opaque to clients: pass it back as `?cursor=` with the same
+/// filters and `sort` to fetch the following page. It is `null` on the last page.
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub limit: i64,
+}
+
+/// Resolved `limit`/`cursor` query parameters.
+///
+/// A cursor holds the sort values of the last row of the previous page, `id`
+/// included, so the next page starts right after that row however many rows
+/// were added or removed before it.
+#[derive(Debug, Clone)]
+pub struct Pagination {
+    pub limit: i64,
+    after: Option<Vec<(String, Option<String>)>>,
+}
+
+impl Pagination {
+    pub fn new(limit: Option<i64>, cursor: Option<&str>) -> Result<Self, AppError> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT);
+        if !(1..=MAX_LIMIT).contains(&limit) {
+            return Err(
+                AppError::bad_request(format!("limit must be between 1 and {MAX_LIMIT}"))
+                    .with_field("limit"),
+            );
+        }
+
+        let after = match cursor.filter(|cursor| !cursor.is_empty()) {
+            Some(cursor) => Some(
+                hex::decode(cursor)
+                    .ok()
+                    .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+                    .ok_or_else(invalid_cursor)?,
+            ),
+            None => None,
+        };
+
+        Ok(Self { limit, after })
+    }
+
+    /// Keep the first `limit` rows of a page fetched by [`Listing::page`], with a
+    /// cursor after the last of them when there were more.
+    pub fn into_page<T: Serialize>(self, listing: &Listing, mut items: Vec<T>) -> Page<T> {
+        let has_more = items.len() as i64 > self.limit;
+        items.truncate(self.limit as usize);
+
+        let next_cursor = match items.last() {
+            Some(last) if has_more => Some(listing.order.cursor(last)),
+            _ => None,
+        };
+
+        Page {
+            items,
+            next_cursor,
+            limit: self.limit,
+        }
+    }
+}
+
+fn invalid_cursor() -> AppError {
+    AppError::bad_request("Invalid cursor").with_field("cursor")
+}
+
+/// SQL type of a sort column, which cursor values are checked against and cast to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Text,
+    Integer,
+    Float,
+    Timestamp,
+}
+
+impl ColumnType {
+    fn sql(self) -> &'static str {
+        match self {
+            ColumnType::Text => "text",
+            ColumnType::Integer => "bigint",
+            ColumnType::Float => "double precision",
+            ColumnType::Timestamp => "timestamptz",
+        }
+    }
+
+    fn parses(self, value: &str) -> bool {
+        match self {
+            ColumnType::Text => true,
+            ColumnType::Integer => value.parse::<i64>().is_ok(),
+            ColumnType::Float => value.parse::<f64>().is_ok(),
+            ColumnType::Timestamp => DateTime::parse_from_rfc3339(value).is_ok(),
+        }
+    }
+}
+
+/// One term of an `ORDER BY`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortColumn {
+    pub column: &'static str, // SQL expression
+    pub field: &'static str,  // Key of the value in the row as serialized
+    pub kind: ColumnType,
+    pub descending: bool,
+    pub nullable: bool, // Sorted NULLS LAST
+}
+
+impl SortColumn {
+    /// An ascending, non-null column serialized under its own name.
+    pub const fn new(column: &'static str, kind: ColumnType) -> Self {
+        Self {
+            column,
+            field: column,
+            kind,
+            descending: false,
+            nullable: false,
+        }
+    }
+
+    pub const fn descending(self) -> Self {
+        Self {
+            descending: true,
+            ..self
+        }
+    }
+
+    pub const fn field(self, field: &'static str) -> Self {
+        Self { field, ..self }
+    }
+
+    fn push_value(&self, query: &mut QueryBuilder<'static, Postgres>, value: &Option<String>) {
+        query
+            .push("CAST(")
+            .push_bind(value.clone())
+            .push(format!(" AS {})", self.kind.sql()));
+    }
+
+    /// `column = value`, where two nulls are equal.
+    fn push_equal(&self, query: &mut QueryBuilder<'static, Postgres>, value: &Option<String>) {
+        query.push(self.column).push(if self.nullable {
+            " IS NOT DISTINCT FROM "
+        } else {
+            " = "
+        });
+        self.push_value(query, value);
+    }
+
+    /// Rows this column sorts after `value`; nulls come last.
+    fn push_after(&self, query: &mut QueryBuilder<'static, Postgres>, value: &Option<String>) {
+        if value.is_none() {
+            query.push("FALSE");
+            return;
+        }
+        if self.nullable {
+            query.push("(");
+        }
+        query
+            .push(self.column)
+            .push(if self.descending { " < " } else { " > " });
+        self.push_value(query, value);
+        if self.nullable {
+            query.push(" OR ").push(self.column).push(" IS NULL)");
+        }
+    }
+}
+
+/// Sortable keys of a listing: public name, SQL column and type.
+pub type SortKey = (&'static str, &'static str, ColumnType);
+
+/// The order of a listing, always ending in a unique column so that a cursor
+/// names exactly one row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Order(Vec<SortColumn>);
+
+impl Order {
+    pub fn new(columns: Vec<SortColumn>) -> Self {
+        Self(columns)
+    }
+
+    /// Parse a `sort` parameter such as `bpm` or `-release_year,title`.
+    ///
+    /// `keys` maps the public sort keys to SQL columns; anything else is rejected so
+    /// the parameter never reaches the query text. `default` is used when `sort` is
+    /// absent, and `id` is always appended as a tiebreaker.
+    pub fn parse(sort: Option<&str>, keys: &[SortKey], default: &str) -> Result<Self, AppError> {
+        let sort = sort.filter(|sort| !sort.is_empty()).unwrap_or(default);
+
+        let mut columns = Vec::new();
+        for key in sort.split(',').map(str::trim).filter(|key| !key.is_empty()) {
+            let (key, descending) = match key.strip_prefix('-') {
+                Some(key) => (key, true),
+                None => (key, false),
+            };
+            let (_, column, kind) =
+                keys.iter()
+                    .find(|(name, _, _)| *name == key)
+                    .ok_or_else(|| {
+                        let names: Vec<&str> = keys.iter().map(|(name, _, _)| *name).collect();
+                        AppError::bad_request(format!(
+                            "Unknown sort key `{key}`, expected one of: {}",
+                            names.join(", ")
+                        ))
+                        .with_field("sort")
+                    })?;
+            columns.push(SortColumn {
+                descending,
+                nullable: true,
+                ..SortColumn::new(column, *kind)
+            });
+        }
+        columns.push(SortColumn::new("id", ColumnType::Integer));
+
+        Ok(Self(columns))
+    }
+
+    /// The `ORDER BY` clause.
+    pub fn sql(&self) -> String {
+        let terms: Vec<String> = self
+            .0
+            .iter()
+            .map(|column| match (column.descending, column.nullable) {
+                (false, false) => column.column.to_string(),
+                (true, false) => format!("{} DESC", column.column),
+                (false, true) => format!("{} ASC NULLS LAST", column.column),
+                (true, true) => format!("{} DESC NULLS LAST", column.column),
+            })
+            .collect();
+        format!(" ORDER BY {}", terms.join(", "))
+    }
+
+    /// Append `AND` the rows that sort after the cursor values: those after the
+    /// first value, or equal to it and after the second, and so on.
+    fn push_after(
+        &self,
+        query: &mut QueryBuilder<'static, Postgres>,
+        after: &[(String, Option<String>)],
+    ) -> Result<(), AppError> {
+        let matches = after.len() == self.0.len()
+            && self.0.iter().zip(after).all(|(column, (field, value))| {
+                column.field == field
+                    && match value {
+                        Some(value) => column.kind.parses(value),
+                        None => column.nullable,
+                    }
+            });
+        if !matches {
+            return Err(invalid_cursor());
+        }
+
+        query.push(" AND (");
+        for (index, (column, (_, value))) in self.0.iter().zip(after).enumerate() {
+            if index > 0 {
+                query.push(" OR ");
+            }
+            query.push("(");
+            for (previous, (_, value)) in self.0.iter().zip(after).take(index) {
+                previous.push_equal(query, value);
+                query.push(" AND ");
+            }
+            column.push_after(query, value);
+            query.push(")");
+        }
+        query.push(")");
+
+        Ok(())
+    }
+
+    /// The cursor naming `row`: its value of every sort column.
+    fn cursor<T: Serialize>(&self, row: &T) -> String {
+        let row = serde_json::to_value(row).unwrap_or_default();
+        let after: Vec<(&str, Option<String>)> = self
+            .0
+            .iter()
+            .map(|column| {
+                let value = match row.get(column.field) {
+                    Some(serde_json::Value::String(value)) => Some(value.clone()),
+                    Some(serde_json::Value::Number(value)) => Some(value.to_string()),
+                    _ => None,
+                };
+                (column.field, value)
+            })
+            .collect();
+        hex::encode(serde_json::to_vec(&after).unwrap_or_default())
+    }
+}
+
+/// A filtered listing query and the order of its rows.
+pub struct Listing {
+    query: QueryBuilder<'static, Postgres>,
+    order: Order,
+}
+
+impl Listing {
+    /// `query` must end inside its `WHERE` clause, so conditions can be appended.
+    pub fn new(query: QueryBuilder<'static, Postgres>, order: Order) -> Self {
+        Self { query, order }
+    }
+
+    /// Every matching row, in order.
+    pub fn all(mut self) -> QueryBuilder<'static, Postgres> {
+        self.query.push(self.order.sql());
+        self.query
+    }
+
+    /// The rows after the cursor, in order, fetching one extra row to detect a
+    /// following page.
+    pub fn page(
+        &mut self,
+        pagination: &Pagination,
+    ) -> Result<&mut QueryBuilder<'static, Postgres>, AppError> {
+        if let Some(after) = &pagination.after {
+            self.order.push_after(&mut self.query, after)?;
+        }
+        self.query
+            .push(self.order.sql())
+            .push(" LIMIT ")
+            .push_bind(pagination.limit + 1);
+
+        Ok(&mut self.query)
+    }
+}
+
+/// `%value%` for an `ILIKE` substring filter, with the wildcards in `value` escaped.
+fn contains(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+/// Append `AND <column> <op> $n` for an optional filter value.
+fn push_condition<T>(query: &mut QueryBuilder<'static, Postgres>, condition: &str, value: Option<T>)
+where
+    T: 'static + sqlx::Encode<'static, Postgres> + sqlx::Type<Postgres> + Send,
+{
+    if let Some(value) = value {
+        query.push(" AND ").push(condition).push_bind(value);
+    }
+}
+
+/// Append the `created_from`/`created_to` bounds shared by every list endpoint.
+fn push_created_range(
+    query: &mut QueryBuilder<'static, Postgres>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) {
+    push_condition(query, "created_at >= ", from);
+    push_condition(query, "created_at <= ", to);
+}
+
+pub fn artists_query(filter: &ArtistFilter) -> Result<Listing, AppError> {
+    let mut query = QueryBuilder::new(
+        "SELECT id, name, country, created_at, updated_at, version FROM artists WHERE deleted_at IS NULL",
+    );
+    push_condition(
+        &mut query,
+        "name ILIKE ",
+        filter.name.as_deref().map(contains),
+    );
+    push_condition(
+        &mut query,
+        "country ILIKE ",
+        filter.country.as_deref().map(contains),
+    );
+    push_created_range(&mut query, filter.created_from, filter.created_to);
+    let order = Order::parse(
+        filter.sort.as_deref(),
+        &[
+            ("name", "name", ColumnType::Text),
+            ("country", "country", ColumnType::Text),
+            ("created_at", "created_at", ColumnType::Timestamp),
+            ("updated_at", "updated_at", ColumnType::Timestamp),
+            ("id", "id", ColumnType::Integer),
+        ],
+        "",
+    )?;
+
+    Ok(Listing::new(query, order))
+}
+
+pub fn albums_query(filter: &AlbumFilter) -> Result<Listing, AppError> {
+    let mut query = QueryBuilder::new(
+        "SELECT id, title, artist_id, release_year, label, format, country, genre, style, created_at, updated_at, version FROM albums WHERE deleted_at IS NULL",
+    );
+    push_condition(&mut query, "artist_id = ", filter.artist_id);
+    push_condition(
+        &mut query,
+        "genre ILIKE ",
+        filter.genre.as_deref().map(contains),
+    );
+    push_condition(
+        &mut query,
+        "style ILIKE ",
+        filter.style.as_deref().map(contains),
+    );
+    push_condition(
+        &mut query,
+        "label ILIKE ",
+        filter.label.as_deref().map(contains),
+    );
+    push_condition(
+        &mut query,
+        "country ILIKE ",
+        filter.country.as_deref().map(contains),
+    );
+    push_condition(&mut query, "release_year >= ", filter.year_from);
+    push_condition(&mut query, "release_year <= ", filter.year_to);
+    push_created_range(&mut query, filter.created_from, filter.created_to);
+    let order = Order::parse(
+        filter.sort.as_deref(),
+        &[
+            ("title", "title", ColumnType::Text),
+            ("release_year", "release_year", ColumnType::Integer),
+            ("label", "label", ColumnType::Text),
+            ("genre", "genre", ColumnType::Text),
+            ("country", "country", ColumnType::Text),
+            ("created_at", "created_at", ColumnType::Timestamp),
+            ("updated_at", "updated_at", ColumnType::Timestamp),
+            ("id", "id", ColumnType::Integer),
+        ],
+        "",
+    )?;
+
+    Ok(Listing::new(query, order))
+}
+
+pub fn tracks_query(filter: &TrackFilter) -> Result<Listing, AppError> {
+    let mut query = QueryBuilder::new(
+        "SELECT id, title, artist_name, album_id, duration_seconds, bpm, tone, position, score, entrega_id, created_at, updated_at, version FROM tracks WHERE deleted_at IS NULL",
+    );
+    push_condition(&mut query, "entrega_id = ", filter.entrega_id);
+    push_condition(&mut query, "album_id = ", filter.album_id);
+    push_condition(
+        &mut query,
+        "artist_name ILIKE ",
+        filter.artist.as_deref().map(contains),
+    );
+    push_condition(&mut query, "bpm >= ", filter.bpm_min);
+    push_condition(&mut query, "bpm <= ", filter.bpm_max);
+    push_condition(&mut query, "tone = ", filter.tone.clone());
+    push_condition(&mut query, "score = ", filter.score.clone());
+    push_created_range(&mut query, filter.created_from, filter.created_to);
+    let order = Order::parse(
+        filter.sort.as_deref(),
+        &[
+            ("title", "title", ColumnType::Text),
+            ("artist", "artist_name", ColumnType::Text),
+            ("bpm", "bpm", ColumnType::Float),
+            ("tone", "tone", ColumnType::Text),
+            ("position", "position", ColumnType::Text),
+            ("score", "score", ColumnType::Text),
+            ("album_id", "album_id", ColumnType::Integer),
+            ("entrega_id", "entrega_id", ColumnType::Integer),
+            ("created_at", "created_at", ColumnType::Timestamp),
+            ("updated_at", "updated_at", ColumnType::Timestamp),
+            ("id", "id", ColumnType::Integer),
+        ],
+        "position",
+    )?;
+
+    Ok(Listing::new(query, order))
+}
+
+pub fn batches_query(filter: &BatchFilter) -> Result<Listing, AppError> {
+    let mut query = QueryBuilder::new(
+        "SELECT id, name, created_at, updated_at, version FROM batches WHERE deleted_at IS NULL",
+    );
+    push_condition(
+        &mut query,
+        "name ILIKE ",
+        filter.name.as_deref().map(contains),
+    );
+    push_created_range(&mut query, filter.created_from, filter.created_to);
+    let order = Order::parse(
+        filter.sort.as_deref(),
+        &[
+            ("name", "name", ColumnType::Text),
+            ("created_at", "created_at", ColumnType::Timestamp),
+            ("updated_at", "updated_at", ColumnType::Timestamp),
+            ("id", "id", ColumnType::Integer),
+        ],
+        "",
+    )?;
+
+    Ok(Listing::new(query, order))
+}
+
+pub fn entregas_query(filter: &EntregaFilter) -> Result<Listing, AppError> {
+    let mut query = QueryBuilder::new(
+        "SELECT id, name, batch_id, created_at, updated_at, version FROM entregas WHERE deleted_at IS NULL",
+    );
+    push_condition(&mut query, "batch_id = ", filter.batch_id);
+    push_condition(
+        &mut query,
+        "name ILIKE ",
+        filter.name.as_deref().map(contains),
+    );
+    push_created_range(&mut query, filter.created_from, filter.created_to);
+    let order = Order::parse(
+        filter.sort.as_deref(),
+        &[
+            ("name", "name", ColumnType::Text),
+            ("batch_id", "batch_id", ColumnType::Integer),
+            ("created_at", "created_at", ColumnType::Timestamp),
+            ("updated_at", "updated_at", ColumnType::Timestamp),
+            ("id", "id", ColumnType::Integer),
+        ],
+        "",
+    )?;
+
+    Ok(Listing::new(query, order))
+}
+
+pub fn live_sets_query(filter: &LiveSetFilter) -> Result<Listing, AppError> {
+    let mut query = QueryBuilder::new(
+        "SELECT id, name, aired_at, notes, created_at, updated_at, version FROM live_sets WHERE TRUE",
+    );
+    push_condition(
+        &mut query,
+        "name ILIKE ",
+        filter.name.as_deref().map(contains),
+    );
+    push_created_range(&mut query, filter.created_from, filter.created_to);
+    let order = Order::parse(
+        filter.sort.as_deref(),
+        &[
+            ("name", "name", ColumnType::Text),
+            ("aired_at", "aired_at", ColumnType::Timestamp),
+            ("created_at", "created_at", ColumnType::Timestamp),
+            ("updated_at", "updated_at", ColumnType::Timestamp),
+            ("id", "id", ColumnType::Integer),
+        ],
+        "",
+    )?;
+
+    Ok(Listing::new(query, order))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEYS: &[SortKey] = &[
+        ("title", "title", ColumnType::Text),
+        ("artist", "artist_name", ColumnType::Text),
+        ("bpm", "bpm", ColumnType::Float),
+    ];
+
+    #[derive(Serialize)]
+    struct Row {
+        id: i64,
+        title: &'static str,
+        bpm: Option<f64>,
+    }
+
+    fn after(order: &Order, cursor: &str) -> Result<String, AppError> {
+        let pagination = Pagination::new(None, Some(cursor))?;
+        let mut listing = Listing::new(
+            QueryBuilder::new("SELECT * FROM tracks WHERE TRUE"),
+            order.clone(),
+        );
+        Ok(listing.page(&pagination)?.sql().to_string())
+    }
+
+    #[test]
+    fn sorts_by_known_keys_with_id_as_tiebreaker() {
+        let sql = |sort, default| Order::parse(sort, KEYS, default).unwrap().sql();
+        assert_eq!(
+            sql(Some("-artist, title"), ""),
+            " ORDER BY artist_name DESC NULLS LAST, title ASC NULLS LAST, id"
+        );
+        assert_eq!(sql(None, "title"), " ORDER BY title ASC NULLS LAST, id");
+        assert_eq!(sql(Some(""), ""), " ORDER BY id");
+    }
+
+    #[test]
+    fn rejects_unknown_sort_keys() {
+        let error = Order::parse(Some("title; DROP TABLE tracks"), KEYS, "").unwrap_err();
+        assert_eq!(error.field.as_deref(), Some("sort"));
+    }
+
+    #[test]
+    fn text_filters_match_literally_anywhere() {
+        assert_eq!(contains("Fresh"), "%Fresh%");
+        assert_eq!(contains("100%_pure\\"), "%100\\%\\_pure\\\\%");
+    }
+
+    #[test]
+    fn pages_after_the_last_row() {
+        let order = Order::parse(Some("-bpm"), KEYS, "").unwrap();
+        let listing = Listing::new(QueryBuilder::new(""), order.clone());
+        let rows = || {
+            vec![
+                Row {
+                    id: 4,
+                    title: "a",
+                    bpm: Some(128.5),
+                },
+                Row {
+                    id: 2,
+                    title: "b",
+                    bpm: None,
+                },
+                Row {
+                    id: 9,
+                    title: "c",
+                    bpm: None,
+                },
+            ]
+        };
+
+        let page = Pagination::new(Some(1), None)
+            .unwrap()
+            .into_page(&listing, rows());
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(
+            after(&order, page.next_cursor.as_deref().unwrap()).unwrap(),
+            "SELECT * FROM tracks WHERE TRUE AND (((bpm < CAST($1 AS double precision) OR bpm IS NULL)) \
+             OR (bpm IS NOT DISTINCT FROM CAST($2 AS double precision) AND id > CAST($3 AS bigint))) \
+             ORDER BY bpm DESC NULLS LAST, id LIMIT $4"
+        );
+
+        // After a null, only the rows with a null and a greater id follow
+        let page = Pagination::new(Some(2), None)
+            .unwrap()
+            .into_page(&listing, rows());
+        assert!(after(&order, page.next_cursor.as_deref().unwrap())
+            .unwrap()
+            .contains("((FALSE) OR"));
+
+        let page = Pagination::new(Some(3), None)
+            .unwrap()
+            .into_page(&listing, rows());
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn rejects_cursors_of_another_sort() {
+        let listing = Listing::new(
+            QueryBuilder::new(""),
+            Order::parse(Some("title"), KEYS, "").unwrap(),
+        );
+        let rows = vec![
+            Row {
+                id: 1,
+                title: "a",
+                bpm: None,
+            },
+            Row {
+                id: 2,
+                title: "b",
+                bpm: None,
+            },
+        ];
+        let cursor = Pagination::new(Some(1), None)
+            .unwrap()
+            .into_page(&listing, rows)
+            .next_cursor
+            .unwrap();
+
+        let other = Order::parse(Some("bpm"), KEYS, "").unwrap();
+        assert_eq!(
+            after(&other, &cursor).unwrap_err().field.as_deref(),
+            Some("cursor")
+        );
+    }
+
+    #[test]
+    fn rejects_out_of_range_pages() {
+        for (limit, cursor, field) in [
+            (Some(0), None, "limit"),
+            (Some(MAX_LIMIT + 1), None, "limit"),
+            (None, Some("not a cursor"), "cursor"),
+            (None, Some("7b7d"), "cursor"),
+        ] {
+            let error = Pagination::new(limit, cursor).unwrap_err();
+            assert_eq!(error.field.as_deref(), Some(field));
+        }
+    }
+}
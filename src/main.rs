@@ -1,7 +1,27 @@
+mod admin;
+mod articles;
+mod artists;
+mod audit;
+mod auth;
 mod db;
+mod error;
+mod etag;
+mod importer;
 mod init;
+mod keys;
+mod listing;
+mod markdown;
 mod models;
+mod normalize;
+mod patch;
 mod routes;
+mod search;
+mod setlist;
+mod snapshot;
+mod spreadsheet;
+mod templates;
+mod trash;
+mod validate;
 
 use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
@@ -12,8 +32,38 @@ use routes::{app_routes, AppState};
 use tower::ServiceBuilder;
 use tower_http::trace::TraceLayer;
 
+/// What to do besides migrating: serve the API, or run one maintenance task and exit.
+enum Command {
+    Serve,
+    Seed { dry_run: bool }, // Apply the seed files, or only print what they would change
+    // Write every table to a JSON file, users and API tokens only when asked for
+    Snapshot { path: PathBuf, include_accounts: bool },
+    Restore(PathBuf), // Load such a file into an empty database
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        [] => Command::Serve,
+        ["seed"] => Command::Seed { dry_run: false },
+        ["seed", "--dry-run"] => Command::Seed { dry_run: true },
+        ["snapshot", path] => Command::Snapshot {
+            path: PathBuf::from(path),
+            include_accounts: false,
+        },
+        ["snapshot", path, "--include-accounts"] => Command::Snapshot {
+            path: PathBuf::from(path),
+            include_accounts: true,
+        },
+        ["restore", path] => Command::Restore(PathBuf::from(path)),
+        _ => {
+            return Err(
+                "usage: revista-sigilosa [seed [--dry-run] | snapshot FILE [--include-accounts] | restore FILE]".into(),
+            )
+        }
+    };
+
     let articles_dir = PathBuf::from("./articles");
     ensure_articles_dir(&articles_dir).await?;
     println!("✓ Articles directory is readable: {}", articles_dir.display());
@@ -23,13 +73,34 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let pool = create_pool(&database_url).await?;
     run_migrations(&pool).await?;
 
-    // Initialize with initial data (only runs if database is empty)
-    init::init_data(&pool).await?;
+    match command {
+        Command::Serve => {}
+        Command::Seed { dry_run } => return init::init_data(&pool, dry_run).await,
+        Command::Snapshot {
+            path,
+            include_accounts,
+        } => return write_snapshot(&pool, &path, include_accounts).await,
+        Command::Restore(path) => return restore_snapshot(&pool, &path).await,
+    }
+
+    // Apply new or changed seed files; SEED_ON_START=false leaves it to `seed`, e.g. in production
+    if init::seed_on_start() {
+        init::init_data(&pool, false).await?;
+    }
+    auth::ensure_admin(&pool).await?;
+
+    let synced = articles::sync_articles(&pool, &articles_dir, "articles-sync")
+        .await
+        .map_err(|e| e as Box<dyn std::error::Error>)?;
+    println!("✓ Synchronized {} article(s)", synced.articles);
 
     let state = AppState { pool, articles_dir };
     let app: Router = app_routes(state).layer(ServiceBuilder::new().layer(TraceLayer::new_for_http()));
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
+    // Writes need a login, so the server can listen beyond localhost with e.g. BIND_ADDR=0.0.0.0:3000
+    let addr: SocketAddr = std::env::var("BIND_ADDR")
+        .unwrap_or_else(|_| "127.0.0.1:3000".to_string())
+        .parse()?;
     let listener = tokio::net::TcpListener::bind(addr).await?;
 
     println!("Server running at http://{addr}");
@@ -46,3 +117,35 @@ async fn ensure_articles_dir(path: &Path) -> Result<(), std::io::Error> {
     let _ = tokio::fs::read_dir(path).await?;
     Ok(())
 }
+
+async fn write_snapshot(
+    pool: &db::DbPool,
+    path: &Path,
+    include_accounts: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let snapshot = snapshot::take(pool, include_accounts).await?;
+    let mut json = serde_json::to_string_pretty(&snapshot)?;
+    json.push('\n');
+    tokio::fs::write(path, json).await?;
+
+    let rows: usize = snapshot.tables.values().map(Vec::len).sum();
+    println!(
+        "✓ Wrote {} table(s), {rows} row(s) at migration {} to {}",
+        snapshot.tables.len(),
+        snapshot.schema_version,
+        path.display()
+    );
+    Ok(())
+}
+
+async fn restore_snapshot(pool: &db::DbPool, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let json = tokio::fs::read_to_string(path).await?;
+    let snapshot: snapshot::Snapshot = serde_json::from_str(&json)?;
+    let restored = snapshot::restore(pool, &snapshot).await?;
+
+    println!("✓ Restored {} taken at {}", path.display(), snapshot.exported_at);
+    for (table, rows) in restored {
+        println!("   → {rows} {table}");
+    }
+    Ok(())
+}
@@ -0,0 +1,427 @@
+use html_escape::encode_text;
+use pulldown_cmark::{html, Event, Options, Parser, Tag, TagEnd, TextMergeStream};
+
+use crate::db::DbPool;
+use crate::models::{Album, Entrega, Track};
+
+/// Render an article's Markdown to HTML, resolving `{{...}}` embeds against the database.
+///
+/// Supported embeds:
+/// - `{{tracklist album=12}}` / `{{tracklist entrega=1}}` — table of tracks
+/// - `{{track 45}}` — single inline track reference
+/// - `{{album 12}}` — album metadata card
+/// - `{{entrega "Primera Entrega"}}` / `{{entrega 1}}` — entrega heading plus its tracklist
+///
+/// Embeds are only read from the article's text, so code spans and code blocks show
+/// them literally. Embeds that cannot be resolved are rendered as an inline error
+/// instead of failing the page.
+pub async fn render_article(pool: &DbPool, source: &str) -> Result<String, sqlx::Error> {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_FOOTNOTES);
+
+    let mut events: Vec<Event> = Vec::new();
+    let mut in_code_block = false;
+    // Where the open paragraph starts, and whether it holds nothing but embeds so far
+    let mut paragraph: Option<(usize, bool)> = None;
+
+    for event in TextMergeStream::new(Parser::new_ext(source, options)) {
+        match &event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            Event::Start(Tag::Paragraph) => paragraph = Some((events.len(), true)),
+            Event::End(TagEnd::Paragraph) => {
+                // Embeds written on their own line are blocks, not paragraph text
+                if let Some((start, true)) = paragraph.take() {
+                    events.remove(start);
+                    continue;
+                }
+            }
+            Event::Text(text) if !in_code_block => {
+                let only_embeds = push_embeds(pool, text, &mut events).await?;
+                if let Some((_, embeds)) = paragraph.as_mut() {
+                    *embeds &= only_embeds;
+                }
+                continue;
+            }
+            Event::SoftBreak | Event::HardBreak => {}
+            _ => {
+                if let Some((_, embeds)) = paragraph.as_mut() {
+                    *embeds = false;
+                }
+            }
+        }
+        events.push(event);
+    }
+
+    let mut output = String::new();
+    html::push_html(&mut output, events.into_iter());
+
+    Ok(output)
+}
+
+/// Push `text` with its embeds resolved to HTML; true when it holds nothing but embeds.
+async fn push_embeds(
+    pool: &DbPool,
+    text: &str,
+    events: &mut Vec<Event<'_>>,
+) -> Result<bool, sqlx::Error> {
+    let mut only_embeds = true;
+    let mut push_text = |text: &str, events: &mut Vec<Event<'_>>| {
+        if !text.is_empty() {
+            only_embeds &= text.trim().is_empty();
+            events.push(Event::Text(text.to_string().into()));
+        }
+    };
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+
+        push_text(&rest[..start], events);
+        let directive = &rest[start + 2..start + 2 + len];
+        let html = match Embed::parse(directive) {
+            Some(embed) => embed.resolve(pool).await?,
+            None => embed_error(&format!("Unknown embed: {}", directive.trim())),
+        };
+        events.push(Event::InlineHtml(html.into()));
+
+        rest = &rest[start + 2 + len + 2..];
+    }
+    push_text(rest, events);
+
+    Ok(only_embeds)
+}
+
+enum Reference {
+    Id(i64),
+    Name(String),
+}
+
+enum Embed {
+    AlbumTracklist(i64),
+    EntregaTracklist(i64),
+    Track(i64),
+    Album(i64),
+    Entrega(Reference),
+}
+
+impl Embed {
+    fn parse(directive: &str) -> Option<Self> {
+        let tokens = tokenize(directive);
+        let (name, args) = tokens.split_first()?;
+
+        match (name.as_str(), args) {
+            ("tracklist", [arg]) => {
+                let (key, value) = arg.split_once('=')?;
+                let id = value.parse().ok()?;
+                match key {
+                    "album" => Some(Embed::AlbumTracklist(id)),
+                    "entrega" => Some(Embed::EntregaTracklist(id)),
+                    _ => None,
+                }
+            }
+            ("track", [id]) => id.parse().ok().map(Embed::Track),
+            ("album", [id]) => id.parse().ok().map(Embed::Album),
+            ("entrega", [reference]) => Some(Embed::Entrega(match reference.parse() {
+                Ok(id) => Reference::Id(id),
+                Err(_) => Reference::Name(reference.clone()),
+            })),
+            _ => None,
+        }
+    }
+
+    async fn resolve(&self, pool: &DbPool) -> Result<String, sqlx::Error> {
+        match self {
+            Embed::AlbumTracklist(album_id) => {
+                let Some(album) = fetch_album(pool, *album_id).await? else {
+                    return Ok(embed_error(&format!("Album {album_id} not found")));
+                };
+                let tracks = sqlx::query_as::<_, Track>(
+                    "SELECT id, title, artist_name, album_id, duration_seconds, bpm, tone, position, score, entrega_id, created_at, updated_at, version FROM tracks WHERE album_id = $1 AND deleted_at IS NULL ORDER BY position, id",
+                )
+                .bind(album_id)
+                .fetch_all(pool)
+                .await?;
+
+                Ok(format!(
+                    "<section class=\"embed tracklist\"><h3>{}</h3>{}</section>",
+                    encode_text(&album.title),
+                    tracklist_table(&tracks)
+                ))
+            }
+            Embed::EntregaTracklist(entrega_id) => {
+                let Some(entrega) = fetch_entrega(pool, &Reference::Id(*entrega_id)).await? else {
+                    return Ok(embed_error(&format!("Entrega {entrega_id} not found")));
+                };
+                let tracks = fetch_entrega_tracks(pool, entrega.id).await?;
+
+                Ok(format!(
+                    "<section class=\"embed tracklist\">{}</section>",
+                    tracklist_table(&tracks)
+                ))
+            }
+            Embed::Track(track_id) => {
+                let track = sqlx::query_as::<_, Track>(
+                    "SELECT id, title, artist_name, album_id, duration_seconds, bpm, tone, position, score, entrega_id, created_at, updated_at, version FROM tracks WHERE id = $1 AND deleted_at IS NULL",
+                )
+                .bind(track_id)
+                .fetch_optional(pool)
+                .await?;
+
+                Ok(match track {
+                    Some(track) => format!(
+                        "<span class=\"embed track\">{} — {}{}</span>",
+                        encode_text(&track.artist_name),
+                        encode_text(&track.title),
+                        track_details(&track)
+                    ),
+                    None => embed_error(&format!("Track {track_id} not found")),
+                })
+            }
+            Embed::Album(album_id) => Ok(match fetch_album(pool, *album_id).await? {
+                Some(album) => album_card(&album),
+                None => embed_error(&format!("Album {album_id} not found")),
+            }),
+            Embed::Entrega(reference) => {
+                let Some(entrega) = fetch_entrega(pool, reference).await? else {
+                    let label = match reference {
+                        Reference::Id(id) => id.to_string(),
+                        Reference::Name(name) => format!("\"{name}\""),
+                    };
+                    return Ok(embed_error(&format!("Entrega {label} not found")));
+                };
+                let tracks = fetch_entrega_tracks(pool, entrega.id).await?;
+
+                Ok(format!(
+                    "<section class=\"embed entrega\"><h3>{}</h3>{}</section>",
+                    encode_text(&entrega.name),
+                    tracklist_table(&tracks)
+                ))
+            }
+        }
+    }
+}
+
+/// Split a directive into whitespace separated tokens, keeping double-quoted strings whole.
+fn tokenize(directive: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in directive.trim().chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+async fn fetch_album(pool: &DbPool, id: i64) -> Result<Option<Album>, sqlx::Error> {
+    sqlx::query_as::<_, Album>(
+        "SELECT id, title, artist_id, release_year, label, format, country, genre, style, created_at, updated_at, version FROM albums WHERE id = $1 AND deleted_at IS NULL",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+async fn fetch_entrega(
+    pool: &DbPool,
+    reference: &Reference,
+) -> Result<Option<Entrega>, sqlx::Error> {
+    match reference {
+        Reference::Id(id) => {
+            sqlx::query_as::<_, Entrega>(
+                "SELECT id, name, batch_id, created_at, updated_at, version FROM entregas WHERE id = $1 AND deleted_at IS NULL",
+            )
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+        }
+        Reference::Name(name) => {
+            sqlx::query_as::<_, Entrega>(
+                "SELECT id, name, batch_id, created_at, updated_at, version FROM entregas WHERE name = $1 AND deleted_at IS NULL ORDER BY id LIMIT 1",
+            )
+            .bind(name)
+            .fetch_optional(pool)
+            .await
+        }
+    }
+}
+
+async fn fetch_entrega_tracks(pool: &DbPool, entrega_id: i64) -> Result<Vec<Track>, sqlx::Error> {
+    sqlx::query_as::<_, Track>(
+        "SELECT id, title, artist_name, album_id, duration_seconds, bpm, tone, position, score, entrega_id, created_at, updated_at, version FROM tracks WHERE entrega_id = $1 AND deleted_at IS NULL ORDER BY album_id, position, id",
+    )
+    .bind(entrega_id)
+    .fetch_all(pool)
+    .await
+}
+
+fn tracklist_table(tracks: &[Track]) -> String {
+    let mut html = String::from(
+        "<table><thead><tr><th>#</th><th>Artist</th><th>Title</th><th>BPM</th><th>Tone</th><th>Score</th></tr></thead><tbody>",
+    );
+    for track in tracks {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            encode_text(track.position.as_deref().unwrap_or("")),
+            encode_text(&track.artist_name),
+            encode_text(&track.title),
+            track.bpm.map(|bpm| format!("{bpm:.2}")).unwrap_or_default(),
+            encode_text(track.tone.as_deref().unwrap_or("")),
+            encode_text(track.score.as_deref().unwrap_or("")),
+        ));
+    }
+    html.push_str("</tbody></table>");
+    html
+}
+
+fn track_details(track: &Track) -> String {
+    let mut details: Vec<String> = Vec::new();
+    if let Some(position) = &track.position {
+        details.push(encode_text(position).into_owned());
+    }
+    if let Some(bpm) = track.bpm {
+        details.push(format!("{bpm:.2} BPM"));
+    }
+    if let Some(tone) = &track.tone {
+        details.push(encode_text(tone).into_owned());
+    }
+    if let Some(score) = &track.score {
+        details.push(encode_text(score).into_owned());
+    }
+
+    if details.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", details.join(", "))
+    }
+}
+
+fn album_card(album: &Album) -> String {
+    let mut html = format!(
+        "<section class=\"embed album\"><h3>{}</h3><dl>",
+        encode_text(&album.title)
+    );
+    let fields = [
+        ("Year", album.release_year.map(|year| year.to_string())),
+        ("Label", album.label.clone()),
+        ("Format", album.format.clone()),
+        ("Country", album.country.clone()),
+        ("Genre", album.genre.clone()),
+        ("Style", album.style.clone()),
+    ];
+    for (label, value) in fields {
+        if let Some(value) = value {
+            html.push_str(&format!("<dt>{label}</dt><dd>{}</dd>", encode_text(&value)));
+        }
+    }
+    html.push_str("</dl></section>");
+    html
+}
+
+fn embed_error(message: &str) -> String {
+    format!(
+        "<span class=\"embed-error\">{}</span>",
+        encode_text(message)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A pool that fails on first use, for articles whose embeds need no lookups.
+    fn offline_pool() -> DbPool {
+        sqlx::postgres::PgPoolOptions::new()
+            .acquire_timeout(std::time::Duration::from_millis(100))
+            .connect_lazy("postgres://nobody@127.0.0.1:1/none")
+            .unwrap()
+    }
+
+    #[test]
+    fn tokenizes_quoted_arguments() {
+        assert_eq!(
+            tokenize(r#" entrega "Primera Entrega" "#),
+            ["entrega", "Primera Entrega"]
+        );
+        assert_eq!(tokenize("tracklist  album=12"), ["tracklist", "album=12"]);
+    }
+
+    #[test]
+    fn parses_embed_directives() {
+        assert!(matches!(
+            Embed::parse("tracklist album=12"),
+            Some(Embed::AlbumTracklist(12))
+        ));
+        assert!(matches!(
+            Embed::parse("tracklist entrega=1"),
+            Some(Embed::EntregaTracklist(1))
+        ));
+        assert!(matches!(Embed::parse("track 45"), Some(Embed::Track(45))));
+        assert!(matches!(Embed::parse("album 12"), Some(Embed::Album(12))));
+        assert!(matches!(
+            Embed::parse("entrega 1"),
+            Some(Embed::Entrega(Reference::Id(1)))
+        ));
+        assert!(matches!(
+            Embed::parse(r#"entrega "Primera Entrega""#),
+            Some(Embed::Entrega(Reference::Name(name))) if name == "Primera Entrega"
+        ));
+
+        for unknown in [
+            "",
+            "track",
+            "track x",
+            "tracklist artist=3",
+            "album 1 2",
+            "video 3",
+        ] {
+            assert!(Embed::parse(unknown).is_none(), "{unknown}");
+        }
+    }
+
+    #[tokio::test]
+    async fn leaves_embeds_in_code_alone() {
+        let source =
+            "Use `{{track 1}}` inline.\n\n```\n{{album 1}}\n```\n\n    {{tracklist album=1}}\n";
+        let html = render_article(&offline_pool(), source).await.unwrap();
+
+        assert!(html.contains("<code>{{track 1}}</code>"), "{html}");
+        assert!(
+            html.contains("<pre><code>{{album 1}}\n</code></pre>"),
+            "{html}"
+        );
+        assert!(
+            html.contains("<pre><code>{{tracklist album=1}}\n</code></pre>"),
+            "{html}"
+        );
+    }
+
+    #[tokio::test]
+    async fn renders_embeds_on_their_own_line_as_blocks() {
+        let source = "Before {{nope}} after.\n\n{{nope}}\n";
+        let html = render_article(&offline_pool(), source).await.unwrap();
+
+        assert_eq!(
+            html,
+            "<p>Before <span class=\"embed-error\">Unknown embed: nope</span> after.</p>\n\
+             <span class=\"embed-error\">Unknown embed: nope</span>"
+        );
+    }
+}
@@ -1,12 +1,65 @@
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
 use sqlx::FromRow;
 
-#[derive(Debug, Serialize, FromRow)]
+use crate::error::AppError;
+use crate::importer::Existing;
+use crate::keys::Key;
+
+#[derive(Debug, Clone, Serialize, FromRow)]
 pub struct Artist {
     pub id: i64,
     pub name: String,
     pub country: Option<String>,
-    pub created_at: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: i32, // Bumped on every update, sent as the ETag
+}
+
+/// A track credited to an artist, with the role of the credit.
+#[derive(Debug, Serialize, FromRow)]
+pub struct ArtistTrack {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub track: Track,
+    pub role: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArtistDetail {
+    #[serde(flatten)]
+    pub artist: Artist,
+    pub tracks: Vec<ArtistTrack>,
+    pub albums: Vec<Album>,
+}
+
+/// Audit record of a duplicate artist merged into `target_artist_id`.
+#[derive(Debug, Serialize, FromRow)]
+pub struct ArtistMerge {
+    pub id: i64,
+    pub target_artist_id: i64,
+    pub source_artist_id: i64,
+    pub source_name: String,
+    pub source_country: Option<String>,
+    pub track_credits_moved: i64,
+    pub albums_moved: i64,
+    pub merged_at: DateTime<Utc>,
+}
+
+/// Artists that probably are the same person, as reported by `/artists/duplicates`.
+#[derive(Debug, Serialize)]
+pub struct DuplicateGroup {
+    pub normalized_names: Vec<String>,
+    pub similarity: f64, // 1.0 when every name normalizes to the same key
+    pub artists: Vec<Artist>,
+}
+
+/// One credit of a track: main, featuring, remixer or producer.
+#[derive(Debug, Serialize, FromRow)]
+pub struct TrackCredit {
+    pub artist_id: i64,
+    pub name: String,
+    pub role: String,
 }
 
 #[derive(Debug, Serialize, FromRow)]
@@ -20,7 +73,9 @@ pub struct Album {
     pub country: Option<String>,
     pub genre: Option<String>,
     pub style: Option<String>,
-    pub created_at: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: i32,
 }
 
 #[derive(Debug, Serialize, FromRow)]
@@ -35,14 +90,32 @@ pub struct Track {
     pub position: Option<String>, // New field for A1, A2, B1, etc.
     pub score: Option<String>,    // New field for emoji scores
     pub entrega_id: Option<i64>,  // Link to entrega/release
-    pub created_at: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: i32,
+    #[sqlx(skip)]
+    pub camelot: Option<String>, // Derived from tone, see Track::with_key_notation
+    #[sqlx(skip)]
+    pub open_key: Option<String>,
+}
+
+impl Track {
+    /// Fill the Camelot and Open Key notations derived from `tone`.
+    pub fn with_key_notation(mut self) -> Self {
+        let key = self.tone.as_deref().and_then(Key::parse);
+        self.camelot = key.map(Key::camelot);
+        self.open_key = key.map(Key::open_key);
+        self
+    }
 }
 
 #[derive(Debug, Serialize, FromRow)]
 pub struct Batch {
     pub id: i64,
     pub name: String,
-    pub created_at: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: i32,
 }
 
 #[derive(Debug, Serialize, FromRow)]
@@ -50,7 +123,153 @@ pub struct Entrega {
     pub id: i64,
     pub name: String,
     pub batch_id: Option<i64>,
-    pub created_at: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: i32,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct LiveSet {
+    pub id: i64,
+    pub name: String,
+    pub aired_at: Option<DateTime<Utc>>,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: i32,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct LiveSetEntry {
+    pub id: i64,
+    pub live_set_id: i64,
+    pub track_id: Option<i64>,
+    pub album_id: Option<i64>,
+    pub offset_seconds: i64, // Seconds from the start of the programme
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: i32,
+}
+
+/// An album with the relations requested through `?include=tracks,artist`.
+/// Relations that were not requested are left out of the JSON.
+#[derive(Debug, Serialize)]
+pub struct AlbumDetail {
+    #[serde(flatten)]
+    pub album: Album,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist: Option<Option<Artist>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tracks: Option<Vec<Track>>, // Ordered by position
+}
+
+/// A track with its album and entrega when included.
+#[derive(Debug, Serialize)]
+pub struct TrackDetail {
+    #[serde(flatten)]
+    pub track: Track,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album: Option<Option<Album>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entrega: Option<Option<Entrega>>,
+}
+
+/// The tracks of an entrega that come from one album, or from none.
+#[derive(Debug, Serialize)]
+pub struct AlbumTracks {
+    pub album: Option<Album>,
+    pub tracks: Vec<Track>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EntregaDetail {
+    #[serde(flatten)]
+    pub entrega: Entrega,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tracks_by_album: Option<Vec<AlbumTracks>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchDetail {
+    #[serde(flatten)]
+    pub batch: Batch,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entregas: Option<Vec<Entrega>>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct Article {
+    pub id: i64,
+    pub slug: String,
+    pub filename: String,
+    pub title: String,
+    pub author: Option<String>,
+    pub status: String, // draft, published or archived
+    pub published_at: Option<DateTime<Utc>>,
+    pub entrega_id: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// An account allowed to log in. The password hash never leaves the database.
+#[derive(Debug, Serialize, FromRow)]
+pub struct User {
+    pub id: i64,
+    pub username: String,
+    pub role: String, // reader, editor or admin
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewUser {
+    pub username: String,
+    pub password: String,
+    pub role: Option<String>, // Defaults to reader
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserPatch {
+    pub password: Option<String>,
+    pub role: Option<String>,
+}
+
+/// An API token for scripts, acting with the role of its owner.
+#[derive(Debug, Serialize, FromRow)]
+pub struct ApiToken {
+    pub id: i64,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewApiToken {
+    pub name: String,
+}
+
+/// A just created token; the secret is only ever shown in this response.
+#[derive(Debug, Serialize)]
+pub struct CreatedApiToken {
+    #[serde(flatten)]
+    pub token: ApiToken,
+    pub secret: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginForm {
+    pub username: String,
+    pub password: String,
+    pub next: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewArtist {
+    pub name: String,
+    pub country: Option<String>,
+    pub created_at: Option<DateTime<Utc>>, // Defaults to now, set it only to keep the date of imported records
 }
 
 #[derive(Debug, Deserialize)]
@@ -63,7 +282,7 @@ pub struct NewAlbum {
     pub country: Option<String>,
     pub genre: Option<String>,
     pub style: Option<String>,
-    pub created_at: String,
+    pub created_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -77,26 +296,386 @@ pub struct NewTrack {
     pub position: Option<String>,
     pub score: Option<String>,
     pub entrega_id: Option<i64>,
-    pub created_at: String,
+    pub created_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct NewBatch {
     pub name: String,
-    pub created_at: String,
+    pub created_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct NewEntrega {
     pub name: String,
     pub batch_id: Option<i64>,
-    pub created_at: String,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MergeArtists {
+    pub artist_ids: Vec<i64>, // Duplicates absorbed by the artist in the path
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DuplicateParams {
+    pub threshold: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewTrackCredit {
+    pub artist_id: i64,
+    pub role: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewLiveSet {
+    pub name: String,
+    pub aired_at: Option<String>, // RFC 3339 timestamp or YYYY-MM-DD, see `aired_at_utc`
+    pub notes: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl NewLiveSet {
+    pub fn aired_at_utc(&self) -> Result<Option<DateTime<Utc>>, AppError> {
+        self.aired_at.as_deref().map(aired_at).transpose()
+    }
+}
+
+/// Parse an RFC 3339 timestamp, or a date taken as midnight UTC.
+pub fn date_or_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|at| at.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map(|date| date.and_time(NaiveTime::MIN).and_utc())
+        })
+        .ok()
+}
+
+fn aired_at(value: &str) -> Result<DateTime<Utc>, AppError> {
+    date_or_timestamp(value).ok_or_else(|| {
+        AppError::bad_request(format!(
+            "`{value}` is not a date (YYYY-MM-DD) or an RFC 3339 timestamp"
+        ))
+        .with_field("aired_at")
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewLiveSetEntry {
+    pub track_id: Option<i64>,
+    pub album_id: Option<i64>,
+    pub offset_seconds: i64,
+    pub note: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+// Bodies of the PATCH endpoints. An absent key keeps the column as it is; for
+// nullable columns an explicit `null` clears it (`Some(None)`).
+
+/// Deserialize a present key as `Some`, so `null` is told apart from a missing key.
+fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ArtistPatch {
+    #[serde(default, deserialize_with = "present")]
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    pub country: Option<Option<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AlbumPatch {
+    #[serde(default, deserialize_with = "present")]
+    pub title: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    pub artist_id: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "present")]
+    pub release_year: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "present")]
+    pub label: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub format: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub country: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub genre: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub style: Option<Option<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TrackPatch {
+    #[serde(default, deserialize_with = "present")]
+    pub title: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    pub artist_name: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    pub album_id: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "present")]
+    pub duration_seconds: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "present")]
+    pub bpm: Option<Option<f64>>,
+    #[serde(default, deserialize_with = "present")]
+    pub tone: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub position: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub score: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub entrega_id: Option<Option<i64>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct BatchPatch {
+    #[serde(default, deserialize_with = "present")]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct EntregaPatch {
+    #[serde(default, deserialize_with = "present")]
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    pub batch_id: Option<Option<i64>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LiveSetPatch {
+    #[serde(default, deserialize_with = "present")]
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    pub aired_at: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub notes: Option<Option<String>>,
+}
+
+impl LiveSetPatch {
+    pub fn aired_at_utc(&self) -> Result<Option<Option<DateTime<Utc>>>, AppError> {
+        self.aired_at
+            .as_ref()
+            .map(|value| value.as_deref().map(aired_at).transpose())
+            .transpose()
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LiveSetEntryPatch {
+    #[serde(default, deserialize_with = "present")]
+    pub track_id: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "present")]
+    pub album_id: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "present")]
+    pub offset_seconds: Option<i64>,
+    #[serde(default, deserialize_with = "present")]
+    pub note: Option<Option<String>>,
+}
+
+// Query parameters for the list endpoints. Every filter also accepts
+// `sort` (comma separated keys, `-` prefix for descending), `limit` and `cursor`.
+// Text filters (name, country, genre, style, label, artist) match case-insensitively
+// anywhere in the value; ids, tone and score match exactly.
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ArtistFilter {
+    pub name: Option<String>,
+    pub country: Option<String>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    pub sort: Option<String>,
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AlbumFilter {
+    pub artist_id: Option<i64>,
+    pub genre: Option<String>,
+    pub style: Option<String>,
+    pub label: Option<String>,
+    pub country: Option<String>,
+    pub year_from: Option<i64>,
+    pub year_to: Option<i64>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    pub sort: Option<String>,
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TrackFilter {
+    pub entrega_id: Option<i64>,
+    pub album_id: Option<i64>,
+    pub artist: Option<String>,
+    pub bpm_min: Option<f64>,
+    pub bpm_max: Option<f64>,
+    pub tone: Option<String>,
+    pub score: Option<String>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    pub sort: Option<String>,
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct BatchFilter {
+    pub name: Option<String>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    pub sort: Option<String>,
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct EntregaFilter {
+    pub batch_id: Option<i64>,
+    pub name: Option<String>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    pub sort: Option<String>,
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LiveSetFilter {
+    pub name: Option<String>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    pub sort: Option<String>,
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+}
+
+/// `?include=` of the detail endpoints, a comma separated list of relations to embed.
+#[derive(Debug, Deserialize)]
+pub struct IncludeParams {
+    pub include: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginParams {
+    pub next: Option<String>, // Where to go after logging in
+}
+
+/// Query parameters of the DELETE endpoints. Without `hard` the record is only
+/// moved to the trash.
+#[derive(Debug, Deserialize)]
+pub struct DeleteParams {
+    #[serde(default)]
+    pub hard: bool,
+    #[serde(default)]
+    pub cascade: bool, // Hard delete dependent rows too instead of refusing
+}
+
+/// Query parameters of `/audit`.
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditFilter {
+    pub table: Option<String>,
+    pub row_id: Option<i64>,
+    pub actor: Option<String>,
+    pub action: Option<String>,
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TrashFilter {
+    #[serde(rename = "type")]
+    pub kinds: Option<String>, // Comma separated: artist,album,track,batch,entrega
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompatibleParams {
+    pub bpm_window: Option<f64>, // Maximum BPM difference, defaults to 4
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompatibleTrack {
+    #[serde(flatten)]
+    pub track: Track,
+    pub relation: &'static str,
+    pub bpm_difference: Option<f64>,
+}
+
+/// Request body for `/setlists/generate`. The pool is either `track_ids` or
+/// every track matching `entrega_id`/`album_id`.
+#[derive(Debug, Deserialize)]
+pub struct GenerateSetlist {
+    pub track_ids: Option<Vec<i64>>,
+    pub entrega_id: Option<i64>,
+    pub album_id: Option<i64>,
+    pub start_track_id: Option<i64>,
+    #[serde(default)]
+    pub bpm_curve: Vec<f64>, // Target BPM points spread evenly across the set
+    pub length: Option<usize>, // Defaults to the whole pool
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ImportPayload {
-    pub albums: Option<Vec<NewAlbum>>,
-    pub tracks: Option<Vec<NewTrack>>,
-    pub batches: Option<Vec<NewBatch>>,
-    pub entregas: Option<Vec<NewEntrega>>,
+    pub albums: Option<Vec<ImportItem<NewAlbum>>>,
+    pub tracks: Option<Vec<ImportItem<NewTrack>>>,
+    pub batches: Option<Vec<ImportItem<NewBatch>>>,
+    pub entregas: Option<Vec<ImportItem<NewEntrega>>>,
+}
+
+/// A record of an import payload, which may name what it references instead of
+/// giving its position in the payload.
+#[derive(Debug, Deserialize)]
+pub struct ImportItem<T> {
+    #[serde(flatten)]
+    pub record: T,
+    #[serde(flatten)]
+    pub refs: ImportRefs,
+    pub deleted_at: Option<DateTime<Utc>>, // Set on trashed rows of a snapshot, which are skipped
+}
+
+/// Natural keys of referenced records: album title, entrega, batch or artist name.
+#[derive(Debug, Default, Deserialize)]
+pub struct ImportRefs {
+    pub album: Option<String>,
+    pub album_artist: Option<String>, // Picks between albums sharing a title; defaults to the track's artist
+    pub entrega: Option<String>,
+    pub batch: Option<String>,
+    pub artist: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportParams {
+    #[serde(default)]
+    pub existing: Existing, // skip (default) or update records already in the database
+    #[serde(default)]
+    pub dry_run: bool, // Roll everything back and only report what would change
+}
+
+/// `/import/csv` query: what the rows are, the usual import options and header renames.
+#[derive(Debug, Deserialize)]
+pub struct CsvImportParams {
+    #[serde(rename = "type")]
+    pub kind: String, // tracks or albums
+    #[serde(default)]
+    pub existing: Existing,
+    #[serde(default)]
+    pub dry_run: bool,
+    pub map: Option<String>, // Header:column pairs, e.g. Artista:artist,Tono:tone
+}
+
+/// `/export/csv` query; the listing's own filters are read alongside it.
+#[derive(Debug, Deserialize)]
+pub struct CsvExportParams {
+    #[serde(rename = "type")]
+    pub kind: String,
 }
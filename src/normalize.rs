@@ -0,0 +1,101 @@
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Similarity from which two different normalized names are reported as likely duplicates.
+pub const DEFAULT_SIMILARITY_THRESHOLD: f64 = 0.85;
+
+/// Reduce an artist name to a comparison key, so spelling variants collapse:
+/// `"Fred  Fresh"`, `"fred fresh"`, `"Fred Fresh (2)"` and `"Fréd Fresh"` all become `fred fresh`.
+///
+/// - drops the Discogs-style numeric disambiguation suffix, e.g. `(2)`
+/// - strips diacritics and lowercases
+/// - treats `&` as `and`, other punctuation as spaces
+/// - collapses whitespace and drops a leading `the`
+pub fn normalize_artist_name(name: &str) -> String {
+    let name = strip_disambiguation(name.trim());
+
+    let folded = name
+        .nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .collect::<String>()
+        .replace('&', " and ");
+    let cleaned: String = folded
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+
+    let words: Vec<&str> = cleaned.split_whitespace().collect();
+    let words = match words.split_first() {
+        Some((&"the", rest)) if !rest.is_empty() => rest,
+        _ => &words[..],
+    };
+
+    words.join(" ")
+}
+
+/// Similarity between two normalized names, from 0.0 (unrelated) to 1.0 (identical).
+/// Based on the edit distance, counting swapped letters as a single edit.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    strsim::normalized_damerau_levenshtein(a, b)
+}
+
+/// Remove a trailing `(<digits>)` disambiguation suffix.
+fn strip_disambiguation(name: &str) -> &str {
+    let Some(open) = name.strip_suffix(')').and_then(|rest| rest.rfind('(')) else {
+        return name;
+    };
+    let digits = &name[open + 1..name.len() - 1];
+    if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+        name[..open].trim_end()
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spelling_variants_share_a_key() {
+        for name in [
+            "Fred Fresh",
+            "fred fresh",
+            "  Fred   Fresh ",
+            "Fred Fresh (2)",
+            "Fréd Fresh",
+            "FRED-FRESH",
+            "The Fred Fresh",
+        ] {
+            assert_eq!(normalize_artist_name(name), "fred fresh", "{name}");
+        }
+    }
+
+    #[test]
+    fn ampersand_reads_as_and() {
+        assert_eq!(
+            normalize_artist_name("Astrocat & Kenny S."),
+            normalize_artist_name("Astrocat and Kenny S")
+        );
+    }
+
+    #[test]
+    fn keeps_what_is_part_of_the_name() {
+        // Only a numeric suffix is a disambiguation, and `The` alone is a name
+        assert_eq!(normalize_artist_name("Prince (Live)"), "prince live");
+        assert_eq!(normalize_artist_name("Area (2a)"), "area 2a");
+        assert_eq!(normalize_artist_name("The"), "the");
+        assert_eq!(normalize_artist_name("Theo Parrish"), "theo parrish");
+        assert_eq!(normalize_artist_name("Mind Phase One"), "mind phase one");
+    }
+
+    #[test]
+    fn similarity_counts_a_swap_as_one_edit() {
+        assert_eq!(similarity("fred fresh", "fred fresh"), 1.0);
+        let swapped = similarity("fred fresh", "fred frehs");
+        assert!(swapped >= DEFAULT_SIMILARITY_THRESHOLD, "{swapped}");
+        assert!(swapped > similarity("fred fresh", "fred frexx"));
+        assert!(similarity("fred fresh", "deadly buddha") < DEFAULT_SIMILARITY_THRESHOLD);
+    }
+}
@@ -0,0 +1,158 @@
+use sqlx::{Postgres, QueryBuilder};
+
+use crate::error::AppError;
+use crate::models::{
+    AlbumPatch, ArtistPatch, BatchPatch, EntregaPatch, LiveSetEntryPatch, LiveSetPatch, TrackPatch,
+};
+
+const ARTIST_COLUMNS: &str = "id, name, country, created_at, updated_at, version";
+const ALBUM_COLUMNS: &str = "id, title, artist_id, release_year, label, format, country, genre, style, created_at, updated_at, version";
+const TRACK_COLUMNS: &str = "id, title, artist_name, album_id, duration_seconds, bpm, tone, position, score, entrega_id, created_at, updated_at, version";
+const BATCH_COLUMNS: &str = "id, name, created_at, updated_at, version";
+const ENTREGA_COLUMNS: &str = "id, name, batch_id, created_at, updated_at, version";
+const LIVE_SET_COLUMNS: &str = "id, name, aired_at, notes, created_at, updated_at, version";
+const LIVE_SET_ENTRY_COLUMNS: &str =
+    "id, live_set_id, track_id, album_id, offset_seconds, note, created_at, updated_at, version";
+
+/// `UPDATE ... SET` touching only the columns present in a PATCH body.
+struct Update {
+    query: QueryBuilder<'static, Postgres>,
+    fields: usize,
+}
+
+impl Update {
+    fn new(table: &str) -> Self {
+        Self {
+            query: QueryBuilder::new(format!("UPDATE {table} SET updated_at = now()")),
+            fields: 0,
+        }
+    }
+
+    /// Append `, column = $n` when the key was present in the body.
+    fn set<T>(&mut self, column: &str, value: Option<T>) -> &mut Self
+    where
+        T: 'static + sqlx::Encode<'static, Postgres> + sqlx::Type<Postgres> + Send,
+    {
+        if let Some(value) = value {
+            self.query.push(format!(", {column} = ")).push_bind(value);
+            self.fields += 1;
+        }
+        self
+    }
+
+    /// Finish with `WHERE <conditions> RETURNING <columns>`; conditions use `$n` binds.
+    fn finish(
+        &mut self,
+        keys: &[(&str, i64)],
+        live_only: bool,
+        returning: &str,
+    ) -> Result<QueryBuilder<'static, Postgres>, AppError> {
+        if self.fields == 0 {
+            return Err(AppError::bad_request("The PATCH body sets no known field"));
+        }
+
+        let mut query = std::mem::replace(&mut self.query, QueryBuilder::new(""));
+        query.push(" WHERE TRUE");
+        for (column, value) in keys {
+            query.push(format!(" AND {column} = ")).push_bind(*value);
+        }
+        if live_only {
+            // Trashed records cannot be edited until they are restored
+            query.push(" AND deleted_at IS NULL");
+        }
+        query.push(format!(" RETURNING {returning}"));
+
+        Ok(query)
+    }
+}
+
+pub fn artist_update(
+    id: i64,
+    patch: ArtistPatch,
+) -> Result<QueryBuilder<'static, Postgres>, AppError> {
+    Update::new("artists")
+        .set("name", patch.name)
+        .set("country", patch.country)
+        .finish(&[("id", id)], true, ARTIST_COLUMNS)
+}
+
+pub fn album_update(
+    id: i64,
+    patch: AlbumPatch,
+) -> Result<QueryBuilder<'static, Postgres>, AppError> {
+    Update::new("albums")
+        .set("title", patch.title)
+        .set("artist_id", patch.artist_id)
+        .set("release_year", patch.release_year)
+        .set("label", patch.label)
+        .set("format", patch.format)
+        .set("country", patch.country)
+        .set("genre", patch.genre)
+        .set("style", patch.style)
+        .finish(&[("id", id)], true, ALBUM_COLUMNS)
+}
+
+pub fn track_update(
+    id: i64,
+    patch: TrackPatch,
+) -> Result<QueryBuilder<'static, Postgres>, AppError> {
+    Update::new("tracks")
+        .set("title", patch.title)
+        .set("artist_name", patch.artist_name)
+        .set("album_id", patch.album_id)
+        .set("duration_seconds", patch.duration_seconds)
+        .set("bpm", patch.bpm)
+        .set("tone", patch.tone)
+        .set("position", patch.position)
+        .set("score", patch.score)
+        .set("entrega_id", patch.entrega_id)
+        .finish(&[("id", id)], true, TRACK_COLUMNS)
+}
+
+pub fn batch_update(
+    id: i64,
+    patch: BatchPatch,
+) -> Result<QueryBuilder<'static, Postgres>, AppError> {
+    Update::new("batches")
+        .set("name", patch.name)
+        .finish(&[("id", id)], true, BATCH_COLUMNS)
+}
+
+pub fn entrega_update(
+    id: i64,
+    patch: EntregaPatch,
+) -> Result<QueryBuilder<'static, Postgres>, AppError> {
+    Update::new("entregas")
+        .set("name", patch.name)
+        .set("batch_id", patch.batch_id)
+        .finish(&[("id", id)], true, ENTREGA_COLUMNS)
+}
+
+pub fn live_set_update(
+    id: i64,
+    patch: LiveSetPatch,
+) -> Result<QueryBuilder<'static, Postgres>, AppError> {
+    let aired_at = patch.aired_at_utc()?;
+    Update::new("live_sets")
+        .set("name", patch.name)
+        .set("aired_at", aired_at)
+        .set("notes", patch.notes)
+        .finish(&[("id", id)], false, LIVE_SET_COLUMNS)
+}
+
+pub fn live_set_entry_update(
+    live_set_id: i64,
+    entry_id: i64,
+    patch: LiveSetEntryPatch,
+) -> Result<QueryBuilder<'static, Postgres>, AppError> {
+    Update::new("live_set_entries")
+        .set("track_id", patch.track_id)
+        .set("album_id", patch.album_id)
+        .set("offset_seconds", patch.offset_seconds)
+        .set("note", patch.note)
+        .finish(
+            &[("id", entry_id), ("live_set_id", live_set_id)],
+            false,
+            LIVE_SET_ENTRY_COLUMNS,
+        )
+}
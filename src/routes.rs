@@ -1,17 +1,71 @@
-use std::ffi::OsStr;
+use std::collections::HashMap;
 use std::path::PathBuf;
 
-use axum::extract::{Form, Path, State};
-use axum::http::StatusCode;
-use axum::response::{Html, Redirect};
-use axum::routing::{get, post, put};
-use axum::{Json, Router};
+use axum::body::Bytes;
+use axum::extract::{Form, State};
+use axum::http::{header, StatusCode, Uri};
+use axum::middleware;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::routing::{delete, get, post, put};
+use axum::Router;
+use axum_extra::extract::cookie::{Cookie, CookieJar};
+use minijinja::context;
+use serde::Deserialize;
 use serde_json::json;
+use sqlx::{FromRow, PgConnection};
 
+use crate::admin;
+use crate::articles;
+use crate::artists;
+use crate::audit::{self, AuditEntry};
+use crate::auth::{self, CurrentUser};
 use crate::db::DbPool;
+use crate::error::{AppError, Json, Path, Query};
+use crate::etag::{IfMatch, Tagged};
+use crate::importer::{self, ImportReport};
+use crate::keys::{Key, Relation};
+use crate::listing::{self, Page, Pagination};
+use crate::markdown;
 use crate::models::{
-    Album, Artist, Batch, Entrega, ImportPayload, NewAlbum, NewBatch, NewEntrega, NewTrack, Track,
+    Album, AlbumDetail, AlbumFilter, AlbumPatch, AlbumTracks, ApiToken, Article, Artist,
+    ArtistDetail, ArtistFilter, ArtistMerge, ArtistPatch, ArtistTrack, AuditFilter, Batch,
+    BatchDetail, BatchFilter, BatchPatch, CompatibleParams, CompatibleTrack, CreatedApiToken,
+    CsvExportParams, CsvImportParams, DeleteParams, DuplicateGroup, DuplicateParams, Entrega,
+    EntregaDetail, EntregaFilter, EntregaPatch, GenerateSetlist, ImportParams, ImportPayload,
+    IncludeParams, LiveSet, LiveSetEntry, LiveSetEntryPatch, LiveSetFilter, LiveSetPatch,
+    LoginForm, LoginParams, MergeArtists, NewAlbum, NewApiToken, NewArtist, NewBatch, NewEntrega,
+    NewLiveSet, NewLiveSetEntry, NewTrack, NewTrackCredit, NewUser, Track, TrackCredit,
+    TrackDetail, TrackFilter, TrackPatch, TrashFilter, User, UserPatch,
 };
+use crate::normalize;
+use crate::patch;
+use crate::search::{self as full_text, SearchHit, SEARCH_KINDS};
+use crate::setlist;
+use crate::snapshot::{self, Snapshot};
+use crate::spreadsheet;
+use crate::templates;
+use crate::trash::{self, Kind, TrashItem, KINDS};
+use crate::validate::Validate;
+
+#[derive(FromRow)]
+struct ArticleWithEntrega {
+    #[sqlx(flatten)]
+    article: Article,
+    entrega_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SearchParams {
+    q: String,
+    #[serde(rename = "type")]
+    kinds: Option<String>, // Comma separated: track,album,artist,article
+    limit: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct CreditRoleParams {
+    role: Option<String>, // Remove only this role, otherwise every credit of the artist
+}
 
 #[derive(Clone)]
 pub struct AppState {
@@ -23,94 +77,492 @@ pub fn app_routes(state: AppState) -> Router {
     Router::new()
         .route("/", get(home))
         .route("/health", get(health))
-        .route("/artists", get(get_artists))
+        .route("/artists", get(get_artists).post(create_artist))
+        .route("/artists/duplicates", get(get_artist_duplicates))
+        .route("/artists/{id}/merge", post(merge_artists))
+        .route("/artists/{id}/merges", get(get_artist_merges))
+        .route(
+            "/artists/{id}",
+            get(get_artist)
+                .put(update_artist)
+                .patch(patch_artist)
+                .delete(delete_artist),
+        )
         .route("/albums", get(get_albums).post(create_album))
-        .route("/albums/{id}", put(update_album))
+        .route(
+            "/albums/{id}",
+            get(get_album)
+                .put(update_album)
+                .patch(patch_album)
+                .delete(delete_album),
+        )
+        .route("/albums/{id}/page", get(album_page))
         .route("/tracks", get(get_tracks).post(create_track))
-        .route("/tracks/{id}", put(update_track))
+        .route(
+            "/tracks/{id}",
+            get(get_track)
+                .put(update_track)
+                .patch(patch_track)
+                .delete(delete_track),
+        )
+        .route(
+            "/tracks/{id}/artists",
+            get(get_track_artists).post(add_track_artist),
+        )
+        .route(
+            "/tracks/{id}/artists/{artist_id}",
+            delete(remove_track_artist),
+        )
+        .route("/tracks/{id}/compatible", get(get_compatible_tracks))
+        .route("/setlists/generate", post(generate_setlist))
         .route("/batches", get(get_batches).post(create_batch))
-        .route("/batches/{id}", put(update_batch))
+        .route(
+            "/batches/{id}",
+            get(get_batch)
+                .put(update_batch)
+                .patch(patch_batch)
+                .delete(delete_batch),
+        )
         .route("/entregas", get(get_entregas).post(create_entrega))
-        .route("/entregas/{id}", put(update_entrega))
+        .route(
+            "/entregas/{id}",
+            get(get_entrega)
+                .put(update_entrega)
+                .patch(patch_entrega)
+                .delete(delete_entrega),
+        )
+        .route("/live-sets", get(get_live_sets).post(create_live_set))
+        .route(
+            "/live-sets/{id}",
+            get(get_live_set)
+                .put(update_live_set)
+                .patch(patch_live_set)
+                .delete(delete_live_set),
+        )
+        .route(
+            "/live-sets/{id}/entries",
+            get(get_live_set_entries).post(create_live_set_entry),
+        )
+        .route(
+            "/live-sets/{id}/entries/{entry_id}",
+            put(update_live_set_entry)
+                .patch(patch_live_set_entry)
+                .delete(delete_live_set_entry),
+        )
+        .route("/trash", get(get_trash))
+        .route("/trash/{kind}/{id}/restore", post(restore_from_trash))
+        .route("/audit", get(get_audit))
+        .route("/audit/{id}/revert", post(revert_audit_entry))
+        .route("/history/{table}/{row_id}", get(get_record_history))
+        .route("/search", get(search))
         .route("/import/json", post(import_json))
-        .route("/admin", get(admin_page))
-        .route("/admin/albums", post(create_album_form))
-        .route("/admin/tracks", post(create_track_form))
-        .route("/admin/batches", post(create_batch_form))
-        .route("/admin/entregas", post(create_entrega_form))
+        .route("/import/csv", post(import_csv))
+        .route("/export/csv", get(export_csv))
+        .route("/export/snapshot", get(export_snapshot))
+        .route("/login", get(login_page).post(login))
+        .route("/logout", post(logout))
+        .route("/users", get(get_users).post(create_user))
+        .route("/users/{id}", delete(delete_user).patch(patch_user))
+        .route("/tokens", get(get_tokens).post(create_token))
+        .route("/tokens/{id}", delete(revoke_token))
+        .merge(admin::routes())
+        .route("/entregas/{id}/articles", get(get_entrega_articles))
+        .route("/entregas/{id}/page", get(entrega_page))
         .route("/articles", get(list_articles))
+        .route("/articles/sync", post(sync_articles))
         .route("/articles/{filename}", get(view_article))
+        .layer(middleware::from_fn_with_state(
+            state.pool.clone(),
+            auth::authorize,
+        ))
         .with_state(state)
 }
 
-async fn home() -> Html<&'static str> {
-    Html(include_str!("../templates/home.html"))
+async fn home(State(state): State<AppState>) -> Result<Html<String>, AppError> {
+    let entregas = sqlx::query_as::<_, Entrega>(
+        "SELECT id, name, batch_id, created_at, updated_at, version FROM entregas WHERE deleted_at IS NULL ORDER BY created_at DESC, id DESC",
+    )
+    .fetch_all(&state.pool)
+    .await?;
+
+    templates::render("home.html", context! { entregas => entregas })
 }
 
 async fn health() -> Json<serde_json::Value> {
     Json(json!({ "status": "ok" }))
 }
 
-async fn get_artists(State(state): State<AppState>) -> Result<Json<Vec<Artist>>, StatusCode> {
-    let artists = sqlx::query_as::<_, Artist>(
-        "SELECT id, name, country, created_at FROM artists ORDER BY id",
+async fn get_artists(
+    State(state): State<AppState>,
+    Query(filter): Query<ArtistFilter>,
+) -> Result<Json<Page<Artist>>, AppError> {
+    let pagination = Pagination::new(filter.limit, filter.cursor.as_deref())?;
+    let mut query = listing::artists_query(&filter)?;
+
+    let items = query
+        .page(&pagination)?
+        .build_query_as::<Artist>()
+        .fetch_all(&state.pool)
+        .await?;
+
+    Ok(Json(pagination.into_page(&query, items)))
+}
+
+async fn get_artist(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+) -> Result<Tagged<ArtistDetail>, AppError> {
+    let artist = sqlx::query_as::<_, Artist>(
+        "SELECT id, name, country, created_at, updated_at, version FROM artists WHERE id = $1 AND deleted_at IS NULL",
+    )
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("Artist", id))?;
+
+    let tracks = sqlx::query_as::<_, ArtistTrack>(
+        "SELECT t.id, t.title, t.artist_name, t.album_id, t.duration_seconds, t.bpm, t.tone, t.position, t.score, t.entrega_id, t.created_at, t.updated_at, t.version, ta.role
+         FROM track_artists ta JOIN tracks t ON t.id = ta.track_id
+         WHERE ta.artist_id = $1 AND t.deleted_at IS NULL ORDER BY t.album_id, t.position, t.id",
     )
+    .bind(id)
+    .fetch_all(&state.pool)
+    .await?
+    .into_iter()
+    .map(|credit| ArtistTrack {
+        track: credit.track.with_key_notation(),
+        role: credit.role,
+    })
+    .collect();
+
+    // Albums released by the artist plus albums containing any of their tracks
+    let albums = sqlx::query_as::<_, Album>(
+        "SELECT id, title, artist_id, release_year, label, format, country, genre, style, created_at, updated_at, version FROM albums
+         WHERE deleted_at IS NULL AND (artist_id = $1
+            OR id IN (SELECT t.album_id FROM track_artists ta JOIN tracks t ON t.id = ta.track_id WHERE ta.artist_id = $1 AND t.deleted_at IS NULL))
+         ORDER BY release_year, id",
+    )
+    .bind(id)
     .fetch_all(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
-    Ok(Json(artists))
+    Ok(Tagged(ArtistDetail {
+        artist,
+        tracks,
+        albums,
+    }))
 }
 
-async fn get_albums(State(state): State<AppState>) -> Result<Json<Vec<Album>>, StatusCode> {
-    let albums = sqlx::query_as::<_, Album>(
-        "SELECT id, title, artist_id, release_year, label, format, country, genre, style, created_at FROM albums ORDER BY id",
+async fn create_artist(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Json(input): Json<NewArtist>,
+) -> Result<(StatusCode, Json<Artist>), AppError> {
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+
+    let artist_id: i64 = sqlx::query_scalar(
+        "INSERT INTO artists (name, country, created_at) VALUES ($1, $2, COALESCE($3, now())) RETURNING id",
     )
-    .fetch_all(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .bind(&input.name)
+    .bind(&input.country)
+    .bind(input.created_at)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let artist = sqlx::query_as::<_, Artist>(
+        "SELECT id, name, country, created_at, updated_at, version FROM artists WHERE id = $1",
+    )
+    .bind(artist_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
 
-    Ok(Json(albums))
+    Ok((StatusCode::CREATED, Json(artist)))
 }
 
-async fn get_tracks(State(state): State<AppState>) -> Result<Json<Vec<Track>>, StatusCode> {
-    let tracks = sqlx::query_as::<_, Track>(
-        "SELECT id, title, artist_name, album_id, duration_seconds, bpm, tone, position, score, entrega_id, created_at FROM tracks ORDER BY position, id",
+async fn update_artist(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    user: CurrentUser,
+    if_match: IfMatch,
+    Json(input): Json<NewArtist>,
+) -> Result<Tagged<Artist>, AppError> {
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+    if_match.check(&mut tx, "artists", "Artist", id).await?;
+
+    let result =
+        sqlx::query("UPDATE artists SET name = $1, country = $2, created_at = COALESCE($3, created_at), updated_at = now() WHERE id = $4 AND deleted_at IS NULL")
+            .bind(&input.name)
+            .bind(&input.country)
+            .bind(input.created_at)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("Artist", id));
+    }
+
+    artists::rename_main_credits(&mut tx, id, &input.name).await?;
+
+    let artist = sqlx::query_as::<_, Artist>(
+        "SELECT id, name, country, created_at, updated_at, version FROM artists WHERE id = $1",
     )
-    .fetch_all(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Tagged(artist))
+}
+
+async fn patch_artist(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    user: CurrentUser,
+    if_match: IfMatch,
+    Json(patch): Json<ArtistPatch>,
+) -> Result<Tagged<Artist>, AppError> {
+    patch.validate()?;
+    let renamed = patch.name.clone();
+
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+    if_match.check(&mut tx, "artists", "Artist", id).await?;
+    let artist = patch::artist_update(id, patch)?
+        .build_query_as::<Artist>()
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::not_found("Artist", id))?;
+
+    if let Some(name) = renamed {
+        artists::rename_main_credits(&mut tx, id, &name).await?;
+    }
+
+    tx.commit().await?;
 
-    Ok(Json(tracks))
+    Ok(Tagged(artist))
+}
+
+async fn delete_artist(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    user: CurrentUser,
+    if_match: IfMatch,
+    Query(params): Query<DeleteParams>,
+) -> Result<Response, AppError> {
+    delete_record(&state, Kind::Artist, id, &user, &if_match, &params).await
 }
 
-async fn get_batches(State(state): State<AppState>) -> Result<Json<Vec<Batch>>, StatusCode> {
-    let batches =
-        sqlx::query_as::<_, Batch>("SELECT id, name, created_at FROM batches ORDER BY id")
-            .fetch_all(&state.pool)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+async fn get_artist_duplicates(
+    State(state): State<AppState>,
+    Query(params): Query<DuplicateParams>,
+) -> Result<Json<Vec<DuplicateGroup>>, AppError> {
+    let threshold = params
+        .threshold
+        .unwrap_or(normalize::DEFAULT_SIMILARITY_THRESHOLD);
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err(
+            AppError::bad_request("threshold must be between 0 and 1").with_field("threshold")
+        );
+    }
 
-    Ok(Json(batches))
+    let groups = artists::find_duplicates(&state.pool, threshold).await?;
+
+    Ok(Json(groups))
 }
 
-async fn get_entregas(State(state): State<AppState>) -> Result<Json<Vec<Entrega>>, StatusCode> {
-    let entregas = sqlx::query_as::<_, Entrega>(
-        "SELECT id, name, batch_id, created_at FROM entregas ORDER BY id",
+async fn merge_artists(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Json(input): Json<MergeArtists>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let mut source_ids = input.artist_ids.clone();
+    source_ids.sort_unstable();
+    source_ids.dedup();
+    if source_ids.is_empty() || source_ids.contains(&id) {
+        return Err(AppError::bad_request(
+            "artist_ids must list at least one artist other than the merge target",
+        )
+        .with_field("artist_ids"));
+    }
+
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+
+    let found: i64 = sqlx::query_scalar(
+        "SELECT count(*) FROM artists WHERE id = ANY($1) AND deleted_at IS NULL",
+    )
+    .bind(
+        source_ids
+            .iter()
+            .copied()
+            .chain(std::iter::once(id))
+            .collect::<Vec<i64>>(),
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if found as usize != source_ids.len() + 1 {
+        return Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            "not_found",
+            "The merge target or one of artist_ids does not exist",
+        ));
+    }
+
+    let merges = artists::merge_artists(&mut tx, id, &source_ids).await?;
+
+    let artist = sqlx::query_as::<_, Artist>(
+        "SELECT id, name, country, created_at, updated_at, version FROM artists WHERE id = $1",
+    )
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(json!({ "artist": artist, "merges": merges })))
+}
+
+async fn get_artist_merges(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<ArtistMerge>>, AppError> {
+    let merges = sqlx::query_as::<_, ArtistMerge>(
+        "SELECT id, target_artist_id, source_artist_id, source_name, source_country, track_credits_moved, albums_moved, merged_at
+         FROM artist_merges WHERE target_artist_id = $1 OR source_artist_id = $1 ORDER BY id",
     )
+    .bind(id)
     .fetch_all(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
+
+    Ok(Json(merges))
+}
+
+async fn get_albums(
+    State(state): State<AppState>,
+    Query(filter): Query<AlbumFilter>,
+) -> Result<Json<Page<Album>>, AppError> {
+    let pagination = Pagination::new(filter.limit, filter.cursor.as_deref())?;
+    let mut query = listing::albums_query(&filter)?;
+
+    let items = query
+        .page(&pagination)?
+        .build_query_as::<Album>()
+        .fetch_all(&state.pool)
+        .await?;
+
+    Ok(Json(pagination.into_page(&query, items)))
+}
+
+async fn get_tracks(
+    State(state): State<AppState>,
+    Query(filter): Query<TrackFilter>,
+) -> Result<Json<Page<Track>>, AppError> {
+    let pagination = Pagination::new(filter.limit, filter.cursor.as_deref())?;
+    let mut query = listing::tracks_query(&filter)?;
+
+    let items = query
+        .page(&pagination)?
+        .build_query_as::<Track>()
+        .fetch_all(&state.pool)
+        .await?
+        .into_iter()
+        .map(Track::with_key_notation)
+        .collect();
+
+    Ok(Json(pagination.into_page(&query, items)))
+}
+
+async fn get_batches(
+    State(state): State<AppState>,
+    Query(filter): Query<BatchFilter>,
+) -> Result<Json<Page<Batch>>, AppError> {
+    let pagination = Pagination::new(filter.limit, filter.cursor.as_deref())?;
+    let mut query = listing::batches_query(&filter)?;
+
+    let items = query
+        .page(&pagination)?
+        .build_query_as::<Batch>()
+        .fetch_all(&state.pool)
+        .await?;
+
+    Ok(Json(pagination.into_page(&query, items)))
+}
+
+async fn get_entregas(
+    State(state): State<AppState>,
+    Query(filter): Query<EntregaFilter>,
+) -> Result<Json<Page<Entrega>>, AppError> {
+    let pagination = Pagination::new(filter.limit, filter.cursor.as_deref())?;
+    let mut query = listing::entregas_query(&filter)?;
+
+    let items = query
+        .page(&pagination)?
+        .build_query_as::<Entrega>()
+        .fetch_all(&state.pool)
+        .await?;
+
+    Ok(Json(pagination.into_page(&query, items)))
+}
+
+async fn get_album(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    Query(params): Query<IncludeParams>,
+) -> Result<Tagged<AlbumDetail>, AppError> {
+    let include = includes(&params, &["tracks", "artist"])?;
+
+    let album = sqlx::query_as::<_, Album>(
+        "SELECT id, title, artist_id, release_year, label, format, country, genre, style, created_at, updated_at, version FROM albums WHERE id = $1 AND deleted_at IS NULL",
+    )
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("Album", id))?;
+
+    let mut detail = AlbumDetail {
+        album,
+        artist: None,
+        tracks: None,
+    };
+
+    if include.contains(&"artist") {
+        let artist = sqlx::query_as::<_, Artist>(
+            "SELECT id, name, country, created_at, updated_at, version FROM artists WHERE id = $1 AND deleted_at IS NULL",
+        )
+        .bind(detail.album.artist_id)
+        .fetch_optional(&state.pool)
+        .await?;
+        detail.artist = Some(artist);
+    }
+
+    if include.contains(&"tracks") {
+        let tracks = sqlx::query_as::<_, Track>(
+            "SELECT id, title, artist_name, album_id, duration_seconds, bpm, tone, position, score, entrega_id, created_at, updated_at, version FROM tracks WHERE album_id = $1 AND deleted_at IS NULL ORDER BY position, id",
+        )
+        .bind(id)
+        .fetch_all(&state.pool)
+        .await?;
+        detail.tracks = Some(tracks.into_iter().map(Track::with_key_notation).collect());
+    }
 
-    Ok(Json(entregas))
+    Ok(Tagged(detail))
 }
 
 async fn create_album(
     State(state): State<AppState>,
+    user: CurrentUser,
     Json(input): Json<NewAlbum>,
-) -> Result<(StatusCode, Json<Album>), StatusCode> {
+) -> Result<(StatusCode, Json<Album>), AppError> {
+    input.validate()?;
+
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+
     let album_id: i64 = sqlx::query_scalar(
-        "INSERT INTO albums (title, artist_id, release_year, label, format, country, genre, style, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING id",
+        "INSERT INTO albums (title, artist_id, release_year, label, format, country, genre, style, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, COALESCE($9, now())) RETURNING id",
     )
     .bind(&input.title)
     .bind(input.artist_id)
@@ -120,18 +572,18 @@ async fn create_album(
     .bind(&input.country)
     .bind(&input.genre)
     .bind(&input.style)
-    .bind(&input.created_at)
-    .fetch_one(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .bind(input.created_at)
+    .fetch_one(&mut *tx)
+    .await?;
 
     let album = sqlx::query_as::<_, Album>(
-        "SELECT id, title, artist_id, release_year, label, format, country, genre, style, created_at FROM albums WHERE id = $1",
+        "SELECT id, title, artist_id, release_year, label, format, country, genre, style, created_at, updated_at, version FROM albums WHERE id = $1",
     )
     .bind(album_id)
-    .fetch_one(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
 
     Ok((StatusCode::CREATED, Json(album)))
 }
@@ -139,10 +591,17 @@ async fn create_album(
 async fn update_album(
     Path(id): Path<i64>,
     State(state): State<AppState>,
+    user: CurrentUser,
+    if_match: IfMatch,
     Json(input): Json<NewAlbum>,
-) -> Result<Json<Album>, StatusCode> {
+) -> Result<Tagged<Album>, AppError> {
+    input.validate()?;
+
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+    if_match.check(&mut tx, "albums", "Album", id).await?;
+
     let result = sqlx::query(
-        "UPDATE albums SET title = $1, artist_id = $2, release_year = $3, label = $4, format = $5, country = $6, genre = $7, style = $8, created_at = $9 WHERE id = $10",
+        "UPDATE albums SET title = $1, artist_id = $2, release_year = $3, label = $4, format = $5, country = $6, genre = $7, style = $8, created_at = COALESCE($9, created_at), updated_at = now() WHERE id = $10 AND deleted_at IS NULL",
     )
     .bind(&input.title)
     .bind(input.artist_id)
@@ -152,33 +611,115 @@ async fn update_album(
     .bind(&input.country)
     .bind(&input.genre)
     .bind(&input.style)
-    .bind(&input.created_at)
+    .bind(input.created_at)
     .bind(id)
-    .execute(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .execute(&mut *tx)
+    .await?;
 
     if result.rows_affected() == 0 {
-        return Err(StatusCode::NOT_FOUND);
+        return Err(AppError::not_found("Album", id));
     }
 
     let album = sqlx::query_as::<_, Album>(
-        "SELECT id, title, artist_id, release_year, label, format, country, genre, style, created_at FROM albums WHERE id = $1",
+        "SELECT id, title, artist_id, release_year, label, format, country, genre, style, created_at, updated_at, version FROM albums WHERE id = $1",
     )
     .bind(id)
-    .fetch_one(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Tagged(album))
+}
+
+async fn patch_album(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    user: CurrentUser,
+    if_match: IfMatch,
+    Json(patch): Json<AlbumPatch>,
+) -> Result<Tagged<Album>, AppError> {
+    patch.validate()?;
+
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+    if_match.check(&mut tx, "albums", "Album", id).await?;
+
+    let album = patch::album_update(id, patch)?
+        .build_query_as::<Album>()
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::not_found("Album", id))?;
+
+    tx.commit().await?;
+
+    Ok(Tagged(album))
+}
+
+async fn delete_album(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    user: CurrentUser,
+    if_match: IfMatch,
+    Query(params): Query<DeleteParams>,
+) -> Result<Response, AppError> {
+    delete_record(&state, Kind::Album, id, &user, &if_match, &params).await
+}
+
+async fn get_track(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    Query(params): Query<IncludeParams>,
+) -> Result<Tagged<TrackDetail>, AppError> {
+    let include = includes(&params, &["album", "entrega"])?;
+
+    let track = sqlx::query_as::<_, Track>(
+        "SELECT id, title, artist_name, album_id, duration_seconds, bpm, tone, position, score, entrega_id, created_at, updated_at, version FROM tracks WHERE id = $1 AND deleted_at IS NULL",
+    )
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("Track", id))?;
+
+    let mut detail = TrackDetail {
+        track: track.with_key_notation(),
+        album: None,
+        entrega: None,
+    };
+
+    if include.contains(&"album") {
+        let album = sqlx::query_as::<_, Album>(
+            "SELECT id, title, artist_id, release_year, label, format, country, genre, style, created_at, updated_at, version FROM albums WHERE id = $1 AND deleted_at IS NULL",
+        )
+        .bind(detail.track.album_id)
+        .fetch_optional(&state.pool)
+        .await?;
+        detail.album = Some(album);
+    }
+
+    if include.contains(&"entrega") {
+        let entrega = sqlx::query_as::<_, Entrega>(
+            "SELECT id, name, batch_id, created_at, updated_at, version FROM entregas WHERE id = $1 AND deleted_at IS NULL",
+        )
+        .bind(detail.track.entrega_id)
+        .fetch_optional(&state.pool)
+        .await?;
+        detail.entrega = Some(entrega);
+    }
 
-    Ok(Json(album))
+    Ok(Tagged(detail))
 }
 
 async fn create_track(
     State(state): State<AppState>,
+    user: CurrentUser,
     Json(input): Json<NewTrack>,
-) -> Result<(StatusCode, Json<Track>), StatusCode> {
+) -> Result<(StatusCode, Json<Track>), AppError> {
+    input.validate()?;
+
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+
     let track_id: i64 = sqlx::query_scalar(
-        "INSERT INTO tracks (title, artist_name, album_id, duration_seconds, bpm, tone, position, score, entrega_id, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) RETURNING id",
+        "INSERT INTO tracks (title, artist_name, album_id, duration_seconds, bpm, tone, position, score, entrega_id, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, COALESCE($10, now())) RETURNING id",
     )
     .bind(&input.title)
     .bind(&input.artist_name)
@@ -189,29 +730,38 @@ async fn create_track(
     .bind(&input.position)
     .bind(&input.score)
     .bind(input.entrega_id)
-    .bind(&input.created_at)
-    .fetch_one(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .bind(input.created_at)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    artists::sync_main_artist(&mut tx, track_id, &input.artist_name).await?;
 
     let track = sqlx::query_as::<_, Track>(
-        "SELECT id, title, artist_name, album_id, duration_seconds, bpm, tone, position, score, entrega_id, created_at FROM tracks WHERE id = $1",
+        "SELECT id, title, artist_name, album_id, duration_seconds, bpm, tone, position, score, entrega_id, created_at, updated_at, version FROM tracks WHERE id = $1",
     )
     .bind(track_id)
-    .fetch_one(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .fetch_one(&mut *tx)
+    .await?;
 
-    Ok((StatusCode::CREATED, Json(track)))
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(track.with_key_notation())))
 }
 
 async fn update_track(
     Path(id): Path<i64>,
     State(state): State<AppState>,
+    user: CurrentUser,
+    if_match: IfMatch,
     Json(input): Json<NewTrack>,
-) -> Result<Json<Track>, StatusCode> {
+) -> Result<Tagged<Track>, AppError> {
+    input.validate()?;
+
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+    if_match.check(&mut tx, "tracks", "Track", id).await?;
+
     let result = sqlx::query(
-        "UPDATE tracks SET title = $1, artist_name = $2, album_id = $3, duration_seconds = $4, bpm = $5, tone = $6, position = $7, score = $8, entrega_id = $9, created_at = $10 WHERE id = $11",
+        "UPDATE tracks SET title = $1, artist_name = $2, album_id = $3, duration_seconds = $4, bpm = $5, tone = $6, position = $7, score = $8, entrega_id = $9, created_at = COALESCE($10, created_at), updated_at = now() WHERE id = $11 AND deleted_at IS NULL",
     )
     .bind(&input.title)
     .bind(&input.artist_name)
@@ -222,387 +772,1571 @@ async fn update_track(
     .bind(&input.position)
     .bind(&input.score)
     .bind(input.entrega_id)
-    .bind(&input.created_at)
+    .bind(input.created_at)
     .bind(id)
-    .execute(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .execute(&mut *tx)
+    .await?;
 
     if result.rows_affected() == 0 {
-        return Err(StatusCode::NOT_FOUND);
+        return Err(AppError::not_found("Track", id));
     }
 
+    artists::sync_main_artist(&mut tx, id, &input.artist_name).await?;
+
     let track = sqlx::query_as::<_, Track>(
-        "SELECT id, title, artist_name, album_id, duration_seconds, bpm, tone, position, score, entrega_id, created_at FROM tracks WHERE id = $1",
+        "SELECT id, title, artist_name, album_id, duration_seconds, bpm, tone, position, score, entrega_id, created_at, updated_at, version FROM tracks WHERE id = $1",
     )
     .bind(id)
-    .fetch_one(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    Ok(Json(track))
-}
+    .fetch_one(&mut *tx)
+    .await?;
 
-async fn create_batch(
-    State(state): State<AppState>,
-    Json(input): Json<NewBatch>,
-) -> Result<(StatusCode, Json<Batch>), StatusCode> {
-    let batch_id: i64 =
-        sqlx::query_scalar("INSERT INTO batches (name, created_at) VALUES ($1, $2) RETURNING id")
-            .bind(&input.name)
-            .bind(&input.created_at)
-            .fetch_one(&state.pool)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    let batch =
-        sqlx::query_as::<_, Batch>("SELECT id, name, created_at FROM batches WHERE id = $1")
-            .bind(batch_id)
-            .fetch_one(&state.pool)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    tx.commit().await?;
 
-    Ok((StatusCode::CREATED, Json(batch)))
+    Ok(Tagged(track.with_key_notation()))
 }
 
-async fn update_batch(
+async fn patch_track(
     Path(id): Path<i64>,
     State(state): State<AppState>,
-    Json(input): Json<NewBatch>,
-) -> Result<Json<Batch>, StatusCode> {
-    let result = sqlx::query("UPDATE batches SET name = $1, created_at = $2 WHERE id = $3")
-        .bind(&input.name)
-        .bind(&input.created_at)
-        .bind(id)
-        .execute(&state.pool)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    if result.rows_affected() == 0 {
-        return Err(StatusCode::NOT_FOUND);
+    user: CurrentUser,
+    if_match: IfMatch,
+    Json(patch): Json<TrackPatch>,
+) -> Result<Tagged<Track>, AppError> {
+    patch.validate()?;
+    let artist_name = patch.artist_name.clone();
+
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+    if_match.check(&mut tx, "tracks", "Track", id).await?;
+    let track = patch::track_update(id, patch)?
+        .build_query_as::<Track>()
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::not_found("Track", id))?;
+
+    if let Some(artist_name) = artist_name {
+        artists::sync_main_artist(&mut tx, id, &artist_name).await?;
     }
 
-    let batch =
-        sqlx::query_as::<_, Batch>("SELECT id, name, created_at FROM batches WHERE id = $1")
-            .bind(id)
-            .fetch_one(&state.pool)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    tx.commit().await?;
 
-    Ok(Json(batch))
+    Ok(Tagged(track.with_key_notation()))
 }
 
-async fn create_entrega(
+async fn delete_track(
+    Path(id): Path<i64>,
     State(state): State<AppState>,
-    Json(input): Json<NewEntrega>,
-) -> Result<(StatusCode, Json<Entrega>), StatusCode> {
-    let entrega_id: i64 = sqlx::query_scalar(
-        "INSERT INTO entregas (name, batch_id, created_at) VALUES ($1, $2, $3) RETURNING id",
-    )
-    .bind(&input.name)
-    .bind(input.batch_id)
-    .bind(&input.created_at)
-    .fetch_one(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    let entrega = sqlx::query_as::<_, Entrega>(
-        "SELECT id, name, batch_id, created_at FROM entregas WHERE id = $1",
-    )
-    .bind(entrega_id)
-    .fetch_one(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    Ok((StatusCode::CREATED, Json(entrega)))
+    user: CurrentUser,
+    if_match: IfMatch,
+    Query(params): Query<DeleteParams>,
+) -> Result<Response, AppError> {
+    delete_record(&state, Kind::Track, id, &user, &if_match, &params).await
 }
 
-async fn update_entrega(
+async fn get_track_artists(
     Path(id): Path<i64>,
     State(state): State<AppState>,
-    Json(input): Json<NewEntrega>,
-) -> Result<Json<Entrega>, StatusCode> {
-    let result =
-        sqlx::query("UPDATE entregas SET name = $1, batch_id = $2, created_at = $3 WHERE id = $4")
-            .bind(&input.name)
-            .bind(input.batch_id)
-            .bind(&input.created_at)
-            .bind(id)
-            .execute(&state.pool)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+) -> Result<Json<Vec<TrackCredit>>, AppError> {
+    ensure_track_exists(&state, id).await?;
 
-    if result.rows_affected() == 0 {
-        return Err(StatusCode::NOT_FOUND);
-    }
-
-    let entrega = sqlx::query_as::<_, Entrega>(
-        "SELECT id, name, batch_id, created_at FROM entregas WHERE id = $1",
+    let credits = sqlx::query_as::<_, TrackCredit>(
+        "SELECT a.id AS artist_id, a.name, ta.role FROM track_artists ta JOIN artists a ON a.id = ta.artist_id
+         WHERE ta.track_id = $1
+         ORDER BY CASE ta.role WHEN 'main' THEN 0 WHEN 'featuring' THEN 1 WHEN 'remixer' THEN 2 ELSE 3 END, a.name",
     )
     .bind(id)
-    .fetch_one(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .fetch_all(&state.pool)
+    .await?;
 
-    Ok(Json(entrega))
+    Ok(Json(credits))
 }
 
-async fn import_json(
+async fn add_track_artist(
+    Path(id): Path<i64>,
     State(state): State<AppState>,
-    Json(payload): Json<ImportPayload>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let mut tx = state
-        .pool
-        .begin()
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    if let Some(items) = payload.albums {
-        for item in items {
-            sqlx::query(
-                "INSERT INTO albums (title, artist_id, release_year, label, format, country, genre, style, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
-            )
-            .bind(&item.title)
-            .bind(item.artist_id)
-            .bind(item.release_year)
-            .bind(&item.label)
-            .bind(&item.format)
-            .bind(&item.country)
-            .bind(&item.genre)
-            .bind(&item.style)
-            .bind(&item.created_at)
-            .execute(&mut *tx)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-        }
-    }
-
-    if let Some(items) = payload.tracks {
-        for item in items {
-            sqlx::query(
-                "INSERT INTO tracks (title, artist_name, album_id, duration_seconds, bpm, tone, position, score, entrega_id, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
-            )
-            .bind(&item.title)
-            .bind(&item.artist_name)
-            .bind(item.album_id)
-            .bind(item.duration_seconds)
-            .bind(item.bpm)
-            .bind(&item.tone)
-            .bind(&item.position)
-            .bind(&item.score)
-            .bind(item.entrega_id)
-            .bind(&item.created_at)
-            .execute(&mut *tx)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-        }
+    user: CurrentUser,
+    Json(input): Json<NewTrackCredit>,
+) -> Result<(StatusCode, Json<TrackCredit>), AppError> {
+    ensure_track_exists(&state, id).await?;
+
+    if !["main", "featuring", "remixer", "producer"].contains(&input.role.as_str()) {
+        return Err(AppError::unprocessable(
+            "role must be one of main, featuring, remixer, producer",
+        )
+        .with_field("role"));
     }
 
-    if let Some(items) = payload.batches {
-        for item in items {
-            sqlx::query("INSERT INTO batches (name, created_at) VALUES ($1, $2)")
-                .bind(&item.name)
-                .bind(&item.created_at)
-                .execute(&mut *tx)
-                .await
-                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-        }
-    }
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
 
-    if let Some(items) = payload.entregas {
-        for item in items {
-            sqlx::query("INSERT INTO entregas (name, batch_id, created_at) VALUES ($1, $2, $3)")
-                .bind(&item.name)
-                .bind(item.batch_id)
-                .bind(&item.created_at)
-                .execute(&mut *tx)
-                .await
-                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-        }
-    }
+    let name: String =
+        sqlx::query_scalar("SELECT name FROM artists WHERE id = $1 AND deleted_at IS NULL")
+            .bind(input.artist_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| {
+                AppError::unprocessable(format!("Artist {} does not exist", input.artist_id))
+                    .with_field("artist_id")
+            })?;
 
-    tx.commit()
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    Ok(Json(json!({ "status": "ok" })))
-}
-
-async fn admin_page() -> Html<&'static str> {
-    Html(
-        r#"
-<h1>Admin</h1>
-<p>Use these forms to insert records quickly.</p>
-
-<h2>Album</h2>
-<form action="/admin/albums" method="post">
-  <input name="title" placeholder="title" required />
-  <input name="artist_id" placeholder="artist_id (optional)" />
-  <input name="release_year" placeholder="release_year (optional)" />
-  <input name="created_at" placeholder="created_at (YYYY-MM-DDTHH:MM:SSZ)" required />
-  <button type="submit">Create album</button>
-</form>
-
-<h2>Track</h2>
-<form action="/admin/tracks" method="post">
-  <input name="title" placeholder="title" required />
-  <input name="album_id" placeholder="album_id (optional)" />
-  <input name="duration_seconds" placeholder="duration_seconds (optional)" />
-  <input name="bpm" placeholder="bpm (optional)" />
-  <input name="tone" placeholder="tone A-G (optional)" />
-  <input name="created_at" placeholder="created_at (YYYY-MM-DDTHH:MM:SSZ)" required />
-  <button type="submit">Create track</button>
-</form>
-
-<h2>Batch</h2>
-<form action="/admin/batches" method="post">
-  <input name="name" placeholder="name" required />
-  <input name="created_at" placeholder="created_at (YYYY-MM-DDTHH:MM:SSZ)" required />
-  <button type="submit">Create batch</button>
-</form>
-
-<h2>Entrega</h2>
-<form action="/admin/entregas" method="post">
-  <input name="name" placeholder="name" required />
-  <input name="batch_id" placeholder="batch_id (optional)" />
-  <input name="created_at" placeholder="created_at (YYYY-MM-DDTHH:MM:SSZ)" required />
-  <button type="submit">Create entrega</button>
-</form>
-"#,
-    )
-}
-
-async fn create_album_form(
-    State(state): State<AppState>,
-    Form(input): Form<NewAlbum>,
-) -> Result<Redirect, StatusCode> {
     sqlx::query(
-        "INSERT INTO albums (title, artist_id, release_year, label, format, country, genre, style, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+        "INSERT INTO track_artists (track_id, artist_id, role) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
     )
-    .bind(&input.title)
+    .bind(id)
     .bind(input.artist_id)
-    .bind(input.release_year)
-    .bind(&input.label)
-    .bind(&input.format)
-    .bind(&input.country)
-    .bind(&input.genre)
-    .bind(&input.style)
-    .bind(&input.created_at)
-    .execute(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    Ok(Redirect::to("/admin"))
+    .bind(&input.role)
+    .execute(&mut *tx)
+    .await?;
+    touch_track(&mut tx, id).await?;
+
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(TrackCredit {
+            artist_id: input.artist_id,
+            name,
+            role: input.role,
+        }),
+    ))
 }
 
-async fn create_track_form(
+/// Credits carry no version of their own, so If-Match is the track's ETag.
+async fn remove_track_artist(
+    Path((id, artist_id)): Path<(i64, i64)>,
     State(state): State<AppState>,
-    Form(input): Form<NewTrack>,
-) -> Result<Redirect, StatusCode> {
-    sqlx::query(
-        "INSERT INTO tracks (title, artist_name, album_id, duration_seconds, bpm, tone, position, score, entrega_id, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+    user: CurrentUser,
+    if_match: IfMatch,
+    Query(params): Query<CreditRoleParams>,
+) -> Result<StatusCode, AppError> {
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+    if_match.check(&mut tx, "tracks", "Track", id).await?;
+
+    let result = sqlx::query(
+        "DELETE FROM track_artists WHERE track_id = $1 AND artist_id = $2 AND ($3::VARCHAR IS NULL OR role = $3)",
     )
-    .bind(&input.title)
-    .bind(&input.artist_name)
-    .bind(input.album_id)
-    .bind(input.duration_seconds)
-    .bind(input.bpm)
-    .bind(&input.tone)
-    .bind(&input.position)
-    .bind(&input.score)
-    .bind(input.entrega_id)
-    .bind(&input.created_at)
-    .execute(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .bind(id)
+    .bind(artist_id)
+    .bind(&params.role)
+    .execute(&mut *tx)
+    .await?;
 
-    Ok(Redirect::to("/admin"))
-}
+    if result.rows_affected() == 0 {
+        return Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            "not_found",
+            format!("Artist {artist_id} is not credited on track {id}"),
+        ));
+    }
+    touch_track(&mut tx, id).await?;
 
-async fn create_batch_form(
-    State(state): State<AppState>,
-    Form(input): Form<NewBatch>,
-) -> Result<Redirect, StatusCode> {
-    sqlx::query("INSERT INTO batches (name, created_at) VALUES ($1, $2)")
-        .bind(&input.name)
-        .bind(&input.created_at)
-        .execute(&state.pool)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    tx.commit().await?;
 
-    Ok(Redirect::to("/admin"))
+    Ok(StatusCode::NO_CONTENT)
 }
 
-async fn create_entrega_form(
-    State(state): State<AppState>,
-    Form(input): Form<NewEntrega>,
-) -> Result<Redirect, StatusCode> {
-    sqlx::query("INSERT INTO entregas (name, batch_id, created_at) VALUES ($1, $2, $3)")
-        .bind(&input.name)
-        .bind(input.batch_id)
-        .bind(&input.created_at)
-        .execute(&state.pool)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    Ok(Redirect::to("/admin"))
+/// Bump a track's version after its credits change, so ETags read before the
+/// change no longer match.
+async fn touch_track(conn: &mut PgConnection, id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE tracks SET updated_at = now() WHERE id = $1")
+        .bind(id)
+        .execute(conn)
+        .await?;
+    Ok(())
 }
 
-async fn list_articles(State(state): State<AppState>) -> Result<Html<String>, StatusCode> {
-    let mut entries = tokio::fs::read_dir(&state.articles_dir)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let mut files: Vec<String> = Vec::new();
+async fn ensure_track_exists(state: &AppState, id: i64) -> Result<(), AppError> {
+    let exists: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM tracks WHERE id = $1 AND deleted_at IS NULL)",
+    )
+    .bind(id)
+    .fetch_one(&state.pool)
+    .await?;
 
-    while let Some(entry) = entries
-        .next_entry()
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    {
-        let path = entry.path();
-        if path.extension() == Some(OsStr::new("md")) {
-            let filename = entry.file_name();
-            if let Some(name) = filename.to_str() {
-                files.push(name.to_string());
+    if exists {
+        Ok(())
+    } else {
+        Err(AppError::not_found("Track", id))
+    }
+}
+
+async fn get_compatible_tracks(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    Query(params): Query<CompatibleParams>,
+) -> Result<Json<Vec<CompatibleTrack>>, AppError> {
+    let track = sqlx::query_as::<_, Track>(
+        "SELECT id, title, artist_name, album_id, duration_seconds, bpm, tone, position, score, entrega_id, created_at, updated_at, version FROM tracks WHERE id = $1 AND deleted_at IS NULL",
+    )
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("Track", id))?;
+
+    // Without a known key there is nothing to match against
+    let key = track.tone.as_deref().and_then(Key::parse).ok_or_else(|| {
+        AppError::unprocessable(format!("Track {id} has no recognized tone")).with_field("tone")
+    })?;
+    let bpm_window = params.bpm_window.unwrap_or(4.0).abs();
+    let limit = params.limit.unwrap_or(50).clamp(1, 500) as usize;
+
+    let candidates = sqlx::query_as::<_, Track>(
+        "SELECT id, title, artist_name, album_id, duration_seconds, bpm, tone, position, score, entrega_id, created_at, updated_at, version FROM tracks
+         WHERE id <> $1 AND deleted_at IS NULL AND tone IS NOT NULL
+           AND ($2::DOUBLE PRECISION IS NULL OR (bpm IS NOT NULL AND abs(bpm - $2) <= $3))",
+    )
+    .bind(id)
+    .bind(track.bpm)
+    .bind(bpm_window)
+    .fetch_all(&state.pool)
+    .await?;
+
+    let mut compatible: Vec<(Relation, CompatibleTrack)> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let relation = key.relation(Key::parse(candidate.tone.as_deref()?)?)?;
+            let bpm_difference = track
+                .bpm
+                .zip(candidate.bpm)
+                .map(|(from, to)| ((to - from) * 100.0).round() / 100.0);
+            Some((
+                relation,
+                CompatibleTrack {
+                    track: candidate.with_key_notation(),
+                    relation: relation.as_str(),
+                    bpm_difference,
+                },
+            ))
+        })
+        .collect();
+
+    compatible.sort_by(|(a_relation, a), (b_relation, b)| {
+        let a_diff = a.bpm_difference.map_or(f64::MAX, f64::abs);
+        let b_diff = b.bpm_difference.map_or(f64::MAX, f64::abs);
+        a_relation
+            .cmp(b_relation)
+            .then(a_diff.total_cmp(&b_diff))
+            .then(a.track.id.cmp(&b.track.id))
+    });
+    compatible.truncate(limit);
+
+    Ok(Json(
+        compatible.into_iter().map(|(_, track)| track).collect(),
+    ))
+}
+
+async fn generate_setlist(
+    State(state): State<AppState>,
+    Json(input): Json<GenerateSetlist>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let pool: Vec<Track> = match &input.track_ids {
+        Some(track_ids) if !track_ids.is_empty() => sqlx::query_as::<_, Track>(
+            "SELECT id, title, artist_name, album_id, duration_seconds, bpm, tone, position, score, entrega_id, created_at, updated_at, version FROM tracks WHERE id = ANY($1) AND deleted_at IS NULL ORDER BY id",
+        )
+        .bind(track_ids)
+        .fetch_all(&state.pool)
+        .await?,
+        Some(_) => return Err(AppError::bad_request("track_ids must not be empty").with_field("track_ids")),
+        None if input.entrega_id.is_some() || input.album_id.is_some() => {
+            let filter = TrackFilter {
+                entrega_id: input.entrega_id,
+                album_id: input.album_id,
+                ..TrackFilter::default()
+            };
+            listing::tracks_query(&filter)?
+                .all()
+                .build_query_as::<Track>()
+                .fetch_all(&state.pool)
+                .await?
+        }
+        None => return Err(AppError::bad_request("Provide track_ids, entrega_id or album_id")),
+    };
+
+    let pool: Vec<Track> = pool.into_iter().map(Track::with_key_notation).collect();
+    let length = input.length.unwrap_or(pool.len());
+    let slots = setlist::generate(pool, input.start_track_id, &input.bpm_curve, length)
+        .ok_or_else(|| {
+            AppError::unprocessable("start_track_id is not part of the track pool")
+                .with_field("start_track_id")
+        })?;
+
+    let total_cost: f64 = slots
+        .iter()
+        .filter_map(|slot| slot.transition.as_ref())
+        .map(|transition| transition.cost)
+        .sum();
+
+    Ok(Json(json!({
+        "tracks": slots,
+        "total_cost": (total_cost * 100.0).round() / 100.0,
+    })))
+}
+
+async fn get_batch(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    Query(params): Query<IncludeParams>,
+) -> Result<Tagged<BatchDetail>, AppError> {
+    let include = includes(&params, &["entregas"])?;
+
+    let batch = sqlx::query_as::<_, Batch>(
+        "SELECT id, name, created_at, updated_at, version FROM batches WHERE id = $1 AND deleted_at IS NULL",
+    )
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("Batch", id))?;
+
+    let mut detail = BatchDetail {
+        batch,
+        entregas: None,
+    };
+
+    if include.contains(&"entregas") {
+        let entregas = sqlx::query_as::<_, Entrega>(
+            "SELECT id, name, batch_id, created_at, updated_at, version FROM entregas WHERE batch_id = $1 AND deleted_at IS NULL ORDER BY id",
+        )
+        .bind(id)
+        .fetch_all(&state.pool)
+        .await?;
+        detail.entregas = Some(entregas);
+    }
+
+    Ok(Tagged(detail))
+}
+
+async fn create_batch(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Json(input): Json<NewBatch>,
+) -> Result<(StatusCode, Json<Batch>), AppError> {
+    input.validate()?;
+
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+
+    let batch_id: i64 = sqlx::query_scalar(
+        "INSERT INTO batches (name, created_at) VALUES ($1, COALESCE($2, now())) RETURNING id",
+    )
+    .bind(&input.name)
+    .bind(input.created_at)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let batch = sqlx::query_as::<_, Batch>(
+        "SELECT id, name, created_at, updated_at, version FROM batches WHERE id = $1",
+    )
+    .bind(batch_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(batch)))
+}
+
+async fn update_batch(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    user: CurrentUser,
+    if_match: IfMatch,
+    Json(input): Json<NewBatch>,
+) -> Result<Tagged<Batch>, AppError> {
+    input.validate()?;
+
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+    if_match.check(&mut tx, "batches", "Batch", id).await?;
+
+    let result = sqlx::query("UPDATE batches SET name = $1, created_at = COALESCE($2, created_at), updated_at = now() WHERE id = $3 AND deleted_at IS NULL")
+        .bind(&input.name)
+        .bind(input.created_at)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("Batch", id));
+    }
+
+    let batch = sqlx::query_as::<_, Batch>(
+        "SELECT id, name, created_at, updated_at, version FROM batches WHERE id = $1",
+    )
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Tagged(batch))
+}
+
+async fn patch_batch(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    user: CurrentUser,
+    if_match: IfMatch,
+    Json(patch): Json<BatchPatch>,
+) -> Result<Tagged<Batch>, AppError> {
+    patch.validate()?;
+
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+    if_match.check(&mut tx, "batches", "Batch", id).await?;
+
+    let batch = patch::batch_update(id, patch)?
+        .build_query_as::<Batch>()
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::not_found("Batch", id))?;
+
+    tx.commit().await?;
+
+    Ok(Tagged(batch))
+}
+
+async fn delete_batch(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    user: CurrentUser,
+    if_match: IfMatch,
+    Query(params): Query<DeleteParams>,
+) -> Result<Response, AppError> {
+    delete_record(&state, Kind::Batch, id, &user, &if_match, &params).await
+}
+
+async fn get_entrega(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    Query(params): Query<IncludeParams>,
+) -> Result<Tagged<EntregaDetail>, AppError> {
+    let include = includes(&params, &["tracks"])?;
+
+    let entrega = sqlx::query_as::<_, Entrega>(
+        "SELECT id, name, batch_id, created_at, updated_at, version FROM entregas WHERE id = $1 AND deleted_at IS NULL",
+    )
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("Entrega", id))?;
+
+    let mut detail = EntregaDetail {
+        entrega,
+        tracks_by_album: None,
+    };
+
+    if include.contains(&"tracks") {
+        // Sorted by album so each album's tracks are contiguous; tracks without one come last
+        let tracks = sqlx::query_as::<_, Track>(
+            "SELECT id, title, artist_name, album_id, duration_seconds, bpm, tone, position, score, entrega_id, created_at, updated_at, version FROM tracks WHERE entrega_id = $1 AND deleted_at IS NULL ORDER BY album_id, position, id",
+        )
+        .bind(id)
+        .fetch_all(&state.pool)
+        .await?;
+
+        let album_ids: Vec<i64> = tracks.iter().filter_map(|track| track.album_id).collect();
+        let mut albums: HashMap<i64, Album> = sqlx::query_as::<_, Album>(
+            "SELECT id, title, artist_id, release_year, label, format, country, genre, style, created_at, updated_at, version FROM albums WHERE id = ANY($1) AND deleted_at IS NULL",
+        )
+        .bind(&album_ids)
+        .fetch_all(&state.pool)
+        .await?
+        .into_iter()
+        .map(|album| (album.id, album))
+        .collect();
+
+        let mut groups: Vec<(Option<i64>, AlbumTracks)> = Vec::new();
+        for track in tracks {
+            match groups.last_mut() {
+                Some((album_id, group)) if *album_id == track.album_id => {
+                    group.tracks.push(track.with_key_notation())
+                }
+                _ => groups.push((
+                    track.album_id,
+                    AlbumTracks {
+                        album: track.album_id.and_then(|album_id| albums.remove(&album_id)),
+                        tracks: vec![track.with_key_notation()],
+                    },
+                )),
             }
         }
+        detail.tracks_by_album = Some(groups.into_iter().map(|(_, group)| group).collect());
     }
 
-    files.sort();
+    Ok(Tagged(detail))
+}
+
+async fn create_entrega(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Json(input): Json<NewEntrega>,
+) -> Result<(StatusCode, Json<Entrega>), AppError> {
+    input.validate()?;
+
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+
+    let entrega_id: i64 = sqlx::query_scalar(
+        "INSERT INTO entregas (name, batch_id, created_at) VALUES ($1, $2, COALESCE($3, now())) RETURNING id",
+    )
+    .bind(&input.name)
+    .bind(input.batch_id)
+    .bind(input.created_at)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let entrega = sqlx::query_as::<_, Entrega>(
+        "SELECT id, name, batch_id, created_at, updated_at, version FROM entregas WHERE id = $1",
+    )
+    .bind(entrega_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(entrega)))
+}
+
+async fn update_entrega(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    user: CurrentUser,
+    if_match: IfMatch,
+    Json(input): Json<NewEntrega>,
+) -> Result<Tagged<Entrega>, AppError> {
+    input.validate()?;
+
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+    if_match.check(&mut tx, "entregas", "Entrega", id).await?;
+
+    let result =
+        sqlx::query("UPDATE entregas SET name = $1, batch_id = $2, created_at = COALESCE($3, created_at), updated_at = now() WHERE id = $4 AND deleted_at IS NULL")
+            .bind(&input.name)
+            .bind(input.batch_id)
+            .bind(input.created_at)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
 
-    let mut html = String::from("<h1>Articles</h1><ul>");
-    for file in files {
-        let encoded = urlencoding::encode(&file);
-        html.push_str(&format!(
-            r#"<li><a href="/articles/{}">{}</a></li>"#,
-            encoded, file
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("Entrega", id));
+    }
+
+    let entrega = sqlx::query_as::<_, Entrega>(
+        "SELECT id, name, batch_id, created_at, updated_at, version FROM entregas WHERE id = $1",
+    )
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Tagged(entrega))
+}
+
+async fn patch_entrega(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    user: CurrentUser,
+    if_match: IfMatch,
+    Json(patch): Json<EntregaPatch>,
+) -> Result<Tagged<Entrega>, AppError> {
+    patch.validate()?;
+
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+    if_match.check(&mut tx, "entregas", "Entrega", id).await?;
+
+    let entrega = patch::entrega_update(id, patch)?
+        .build_query_as::<Entrega>()
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::not_found("Entrega", id))?;
+
+    tx.commit().await?;
+
+    Ok(Tagged(entrega))
+}
+
+async fn delete_entrega(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    user: CurrentUser,
+    if_match: IfMatch,
+    Query(params): Query<DeleteParams>,
+) -> Result<Response, AppError> {
+    delete_record(&state, Kind::Entrega, id, &user, &if_match, &params).await
+}
+
+/// Soft delete by default; `hard` removes the row, refusing while other rows
+/// reference it unless `cascade` is set.
+async fn delete_record(
+    state: &AppState,
+    kind: Kind,
+    id: i64,
+    user: &CurrentUser,
+    if_match: &IfMatch,
+    params: &DeleteParams,
+) -> Result<Response, AppError> {
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+    if_match
+        .check(&mut tx, kind.table(), kind.resource(), id)
+        .await?;
+
+    if !params.hard {
+        if !trash::soft_delete(&mut tx, kind, id).await? {
+            return Err(AppError::not_found(kind.resource(), id));
+        }
+        tx.commit().await?;
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    }
+
+    if !trash::exists(&mut tx, kind, id).await? {
+        return Err(AppError::not_found(kind.resource(), id));
+    }
+
+    let dependents = trash::dependents(&mut tx, kind, id).await?;
+    if !dependents.is_empty() && !params.cascade {
+        let references: Vec<String> = dependents
+            .iter()
+            .map(|(what, count)| format!("{count} {what}"))
+            .collect();
+        return Err(AppError::new(
+            StatusCode::CONFLICT,
+            "still_referenced",
+            format!(
+                "{} {id} is referenced by {}; pass cascade=true to delete them too",
+                kind.resource(),
+                references.join(", ")
+            ),
         ));
     }
-    html.push_str("</ul>");
 
-    Ok(Html(html))
+    let removed = trash::hard_delete(&mut tx, kind, id).await?;
+    tx.commit().await?;
+
+    Ok(Json(json!({ "deleted": removed })).into_response())
+}
+
+async fn get_trash(
+    State(state): State<AppState>,
+    Query(filter): Query<TrashFilter>,
+) -> Result<Json<Page<TrashItem>>, AppError> {
+    let kinds = match &filter.kinds {
+        Some(names) => names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                Kind::parse(name).ok_or_else(|| {
+                    AppError::bad_request(format!(
+                        "Unknown type `{name}`, expected artist, album, track, batch or entrega"
+                    ))
+                    .with_field("type")
+                })
+            })
+            .collect::<Result<Vec<Kind>, AppError>>()?,
+        None => KINDS.to_vec(),
+    };
+
+    let pagination = Pagination::new(filter.limit, filter.cursor.as_deref())?;
+    let mut query = trash::trash_query(&kinds);
+
+    let items = query
+        .page(&pagination)?
+        .build_query_as::<TrashItem>()
+        .fetch_all(&state.pool)
+        .await?;
+
+    Ok(Json(pagination.into_page(&query, items)))
+}
+
+async fn restore_from_trash(
+    Path((kind, id)): Path<(String, i64)>,
+    State(state): State<AppState>,
+    user: CurrentUser,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let kind = Kind::parse(&kind).ok_or_else(|| {
+        AppError::new(
+            StatusCode::NOT_FOUND,
+            "not_found",
+            format!("Unknown type `{kind}`"),
+        )
+    })?;
+
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+    if !trash::restore(&mut tx, kind, id).await? {
+        return Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            "not_found",
+            format!("{} {id} is not in the trash", kind.resource()),
+        ));
+    }
+    tx.commit().await?;
+
+    Ok(Json(
+        json!({ "type": kind.as_str(), "id": id, "status": "restored" }),
+    ))
+}
+
+async fn get_audit(
+    State(state): State<AppState>,
+    Query(filter): Query<AuditFilter>,
+) -> Result<Json<Page<AuditEntry>>, AppError> {
+    let pagination = Pagination::new(filter.limit, filter.cursor.as_deref())?;
+    let mut query = audit::audit_query(&filter)?;
+
+    let items = query
+        .page(&pagination)?
+        .build_query_as::<AuditEntry>()
+        .fetch_all(&state.pool)
+        .await?;
+
+    Ok(Json(pagination.into_page(&query, items)))
+}
+
+async fn get_record_history(
+    Path((table, row_id)): Path<(String, i64)>,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<AuditEntry>>, AppError> {
+    let mut conn = state.pool.acquire().await?;
+    let entries = audit::history(&mut conn, &table, row_id).await?;
+
+    Ok(Json(entries))
+}
+
+async fn revert_audit_entry(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    user: CurrentUser,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+    let entry = audit::revert(&mut tx, id).await?;
+    tx.commit().await?;
+
+    Ok(Json(json!({ "status": "reverted", "entry": entry })))
+}
+
+async fn get_live_sets(
+    State(state): State<AppState>,
+    Query(filter): Query<LiveSetFilter>,
+) -> Result<Json<Page<LiveSet>>, AppError> {
+    let pagination = Pagination::new(filter.limit, filter.cursor.as_deref())?;
+    let mut query = listing::live_sets_query(&filter)?;
+
+    let items = query
+        .page(&pagination)?
+        .build_query_as::<LiveSet>()
+        .fetch_all(&state.pool)
+        .await?;
+
+    Ok(Json(pagination.into_page(&query, items)))
+}
+
+async fn get_live_set(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+) -> Result<Tagged<LiveSet>, AppError> {
+    let live_set = sqlx::query_as::<_, LiveSet>(
+        "SELECT id, name, aired_at, notes, created_at, updated_at, version FROM live_sets WHERE id = $1",
+    )
+    .bind(id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("Live set", id))?;
+
+    Ok(Tagged(live_set))
+}
+
+async fn create_live_set(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Json(input): Json<NewLiveSet>,
+) -> Result<(StatusCode, Json<LiveSet>), AppError> {
+    input.validate()?;
+    let aired_at = input.aired_at_utc()?;
+
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+
+    let live_set_id: i64 = sqlx::query_scalar(
+        "INSERT INTO live_sets (name, aired_at, notes, created_at) VALUES ($1, $2, $3, COALESCE($4, now())) RETURNING id",
+    )
+    .bind(&input.name)
+    .bind(aired_at)
+    .bind(&input.notes)
+    .bind(input.created_at)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let live_set = sqlx::query_as::<_, LiveSet>(
+        "SELECT id, name, aired_at, notes, created_at, updated_at, version FROM live_sets WHERE id = $1",
+    )
+    .bind(live_set_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(live_set)))
+}
+
+async fn update_live_set(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    user: CurrentUser,
+    if_match: IfMatch,
+    Json(input): Json<NewLiveSet>,
+) -> Result<Tagged<LiveSet>, AppError> {
+    input.validate()?;
+    let aired_at = input.aired_at_utc()?;
+
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+    if_match.check(&mut tx, "live_sets", "Live set", id).await?;
+
+    let result = sqlx::query(
+        "UPDATE live_sets SET name = $1, aired_at = $2, notes = $3, created_at = COALESCE($4, created_at), updated_at = now() WHERE id = $5",
+    )
+    .bind(&input.name)
+    .bind(aired_at)
+    .bind(&input.notes)
+    .bind(input.created_at)
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("Live set", id));
+    }
+
+    let live_set = sqlx::query_as::<_, LiveSet>(
+        "SELECT id, name, aired_at, notes, created_at, updated_at, version FROM live_sets WHERE id = $1",
+    )
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Tagged(live_set))
+}
+
+async fn patch_live_set(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    user: CurrentUser,
+    if_match: IfMatch,
+    Json(patch): Json<LiveSetPatch>,
+) -> Result<Tagged<LiveSet>, AppError> {
+    patch.validate()?;
+
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+    if_match.check(&mut tx, "live_sets", "Live set", id).await?;
+
+    let live_set = patch::live_set_update(id, patch)?
+        .build_query_as::<LiveSet>()
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::not_found("Live set", id))?;
+
+    tx.commit().await?;
+
+    Ok(Tagged(live_set))
 }
 
+async fn delete_live_set(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    user: CurrentUser,
+    if_match: IfMatch,
+) -> Result<StatusCode, AppError> {
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+    if_match.check(&mut tx, "live_sets", "Live set", id).await?;
+
+    // Entries are removed by the ON DELETE CASCADE on live_set_entries
+    let result = sqlx::query("DELETE FROM live_sets WHERE id = $1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("Live set", id));
+    }
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_live_set_entries(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<LiveSetEntry>>, AppError> {
+    ensure_live_set_exists(&state, id).await?;
+
+    let entries = sqlx::query_as::<_, LiveSetEntry>(
+        "SELECT id, live_set_id, track_id, album_id, offset_seconds, note, created_at, updated_at, version FROM live_set_entries WHERE live_set_id = $1 ORDER BY offset_seconds, id",
+    )
+    .bind(id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(entries))
+}
+
+async fn create_live_set_entry(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Json(input): Json<NewLiveSetEntry>,
+) -> Result<(StatusCode, Json<LiveSetEntry>), AppError> {
+    input.validate()?;
+
+    ensure_live_set_exists(&state, id).await?;
+
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+
+    let entry_id: i64 = sqlx::query_scalar(
+        "INSERT INTO live_set_entries (live_set_id, track_id, album_id, offset_seconds, note, created_at) VALUES ($1, $2, $3, $4, $5, COALESCE($6, now())) RETURNING id",
+    )
+    .bind(id)
+    .bind(input.track_id)
+    .bind(input.album_id)
+    .bind(input.offset_seconds)
+    .bind(&input.note)
+    .bind(input.created_at)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let entry = sqlx::query_as::<_, LiveSetEntry>(
+        "SELECT id, live_set_id, track_id, album_id, offset_seconds, note, created_at, updated_at, version FROM live_set_entries WHERE id = $1",
+    )
+    .bind(entry_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(entry)))
+}
+
+async fn update_live_set_entry(
+    Path((id, entry_id)): Path<(i64, i64)>,
+    State(state): State<AppState>,
+    user: CurrentUser,
+    if_match: IfMatch,
+    Json(input): Json<NewLiveSetEntry>,
+) -> Result<Tagged<LiveSetEntry>, AppError> {
+    input.validate()?;
+
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+    if_match
+        .check(&mut tx, "live_set_entries", "Live set entry", entry_id)
+        .await?;
+
+    let result = sqlx::query(
+        "UPDATE live_set_entries SET track_id = $1, album_id = $2, offset_seconds = $3, note = $4, created_at = COALESCE($5, created_at), updated_at = now() WHERE id = $6 AND live_set_id = $7",
+    )
+    .bind(input.track_id)
+    .bind(input.album_id)
+    .bind(input.offset_seconds)
+    .bind(&input.note)
+    .bind(input.created_at)
+    .bind(entry_id)
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("Live set entry", entry_id));
+    }
+
+    let entry = sqlx::query_as::<_, LiveSetEntry>(
+        "SELECT id, live_set_id, track_id, album_id, offset_seconds, note, created_at, updated_at, version FROM live_set_entries WHERE id = $1",
+    )
+    .bind(entry_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Tagged(entry))
+}
+
+async fn patch_live_set_entry(
+    Path((id, entry_id)): Path<(i64, i64)>,
+    State(state): State<AppState>,
+    user: CurrentUser,
+    if_match: IfMatch,
+    Json(patch): Json<LiveSetEntryPatch>,
+) -> Result<Tagged<LiveSetEntry>, AppError> {
+    patch.validate()?;
+
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+    if_match
+        .check(&mut tx, "live_set_entries", "Live set entry", entry_id)
+        .await?;
+
+    let entry = patch::live_set_entry_update(id, entry_id, patch)?
+        .build_query_as::<LiveSetEntry>()
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::not_found("Live set entry", entry_id))?;
+
+    tx.commit().await?;
+
+    Ok(Tagged(entry))
+}
+
+async fn delete_live_set_entry(
+    Path((id, entry_id)): Path<(i64, i64)>,
+    State(state): State<AppState>,
+    user: CurrentUser,
+    if_match: IfMatch,
+) -> Result<StatusCode, AppError> {
+    let mut tx = audit::begin(&state.pool, &user.username).await?;
+    if_match
+        .check(&mut tx, "live_set_entries", "Live set entry", entry_id)
+        .await?;
+
+    let result = sqlx::query("DELETE FROM live_set_entries WHERE id = $1 AND live_set_id = $2")
+        .bind(entry_id)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("Live set entry", entry_id));
+    }
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn ensure_live_set_exists(state: &AppState, id: i64) -> Result<(), AppError> {
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM live_sets WHERE id = $1)")
+        .bind(id)
+        .fetch_one(&state.pool)
+        .await?;
+
+    if exists {
+        Ok(())
+    } else {
+        Err(AppError::not_found("Live set", id))
+    }
+}
+
+async fn search(
+    State(state): State<AppState>,
+    Query(params): Query<SearchParams>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let query = params.q.trim();
+    if query.is_empty() {
+        return Err(AppError::bad_request("q must not be empty").with_field("q"));
+    }
+
+    let kinds: Vec<String> = match &params.kinds {
+        Some(kinds) => kinds
+            .split(',')
+            .map(|kind| kind.trim().to_string())
+            .filter(|kind| !kind.is_empty())
+            .collect(),
+        None => SEARCH_KINDS.iter().map(|kind| kind.to_string()).collect(),
+    };
+    if kinds
+        .iter()
+        .any(|kind| !SEARCH_KINDS.contains(&kind.as_str()))
+    {
+        return Err(AppError::bad_request(format!(
+            "type must be a comma separated list of {}",
+            SEARCH_KINDS.join(", ")
+        ))
+        .with_field("type"));
+    }
+
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+    let hits: Vec<SearchHit> = full_text::search(&state.pool, query, &kinds, limit).await?;
+
+    Ok(Json(json!({ "query": query, "hits": hits })))
+}
+
+async fn import_json(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Query(params): Query<ImportParams>,
+    Json(payload): Json<ImportPayload>,
+) -> Result<Json<ImportReport>, AppError> {
+    run_import(&state.pool, &user, &payload, &params)
+        .await
+        .map(Json)
+}
+
+async fn import_csv(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Query(params): Query<CsvImportParams>,
+    body: Bytes,
+) -> Result<Json<ImportReport>, AppError> {
+    let kind = spreadsheet::kind(&params.kind)?;
+    let payload = spreadsheet::parse(kind, &body, params.map.as_deref())?;
+    let options = ImportParams {
+        existing: params.existing,
+        dry_run: params.dry_run,
+    };
+
+    run_import(&state.pool, &user, &payload, &options)
+        .await
+        .map(Json)
+}
+
+/// Import in one audited transaction, rolled back on a dry run.
+async fn run_import(
+    pool: &DbPool,
+    user: &CurrentUser,
+    payload: &ImportPayload,
+    params: &ImportParams,
+) -> Result<ImportReport, AppError> {
+    let mut tx = audit::begin(pool, &user.username).await?;
+    let report = importer::import(&mut tx, payload, params).await?;
+    if params.dry_run {
+        tx.rollback().await?;
+    } else {
+        tx.commit().await?;
+    }
+
+    Ok(report)
+}
+
+async fn export_csv(
+    State(state): State<AppState>,
+    Query(params): Query<CsvExportParams>,
+    uri: Uri,
+) -> Result<Response, AppError> {
+    let kind = spreadsheet::kind(&params.kind)?;
+    let body = spreadsheet::export(&state.pool, kind, &uri).await?;
+    let disposition = format!("attachment; filename=\"{}.csv\"", kind.table());
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+async fn export_snapshot(State(state): State<AppState>) -> Result<Json<Snapshot>, AppError> {
+    Ok(Json(snapshot::take(&state.pool, false).await?))
+}
+
+async fn login_page(Query(params): Query<LoginParams>) -> Result<Html<String>, AppError> {
+    login_form(safe_next(params.next.as_deref()), None)
+}
+
+async fn login(
+    jar: CookieJar,
+    State(state): State<AppState>,
+    Form(form): Form<LoginForm>,
+) -> Result<Response, AppError> {
+    let next = safe_next(form.next.as_deref()).to_string();
+
+    let account: Option<(i64, String)> =
+        sqlx::query_as("SELECT id, password_hash FROM users WHERE username = $1")
+            .bind(form.username.trim())
+            .fetch_optional(&state.pool)
+            .await?;
+    let user_id = match account {
+        Some((id, hash)) if auth::verify_password(&hash, &form.password) => id,
+        _ => {
+            return Ok((
+                StatusCode::UNAUTHORIZED,
+                login_form(&next, Some("Invalid username or password"))?,
+            )
+                .into_response());
+        }
+    };
+
+    let token = auth::new_token();
+    sqlx::query(
+        "INSERT INTO sessions (token_hash, user_id, expires_at) VALUES ($1, $2, now() + make_interval(days => $3))",
+    )
+    .bind(auth::token_hash(&token))
+    .bind(user_id)
+    .bind(auth::SESSION_DAYS)
+    .execute(&state.pool)
+    .await?;
+
+    Ok((jar.add(auth::session_cookie(token)), Redirect::to(&next)).into_response())
+}
+
+async fn logout(
+    jar: CookieJar,
+    State(state): State<AppState>,
+) -> Result<(CookieJar, Redirect), AppError> {
+    if let Some(cookie) = jar.get(auth::SESSION_COOKIE) {
+        sqlx::query("DELETE FROM sessions WHERE token_hash = $1")
+            .bind(auth::token_hash(cookie.value()))
+            .execute(&state.pool)
+            .await?;
+    }
+
+    Ok((
+        jar.remove(Cookie::build(auth::SESSION_COOKIE).path("/")),
+        Redirect::to("/login"),
+    ))
+}
+
+fn login_form(next: &str, error: Option<&str>) -> Result<Html<String>, AppError> {
+    templates::render("login.html", context! { next => next, error => error })
+}
+
+/// Only follow local paths after login, never another site.
+fn safe_next(next: Option<&str>) -> &str {
+    match next {
+        Some(next) if next.starts_with('/') && !next.starts_with("//") && !next.contains('\\') => {
+            next
+        }
+        _ => "/admin",
+    }
+}
+
+async fn get_users(State(state): State<AppState>) -> Result<Json<Vec<User>>, AppError> {
+    let users = sqlx::query_as::<_, User>(
+        "SELECT id, username, role, created_at, updated_at FROM users ORDER BY username",
+    )
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(users))
+}
+
+async fn create_user(
+    State(state): State<AppState>,
+    Json(input): Json<NewUser>,
+) -> Result<(StatusCode, Json<User>), AppError> {
+    input.validate()?;
+    let hash = auth::hash_password(&input.password)?;
+
+    let user = sqlx::query_as::<_, User>(
+        "INSERT INTO users (username, password_hash, role) VALUES ($1, $2, COALESCE($3, 'reader'))
+         RETURNING id, username, role, created_at, updated_at",
+    )
+    .bind(input.username.trim())
+    .bind(&hash)
+    .bind(&input.role)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(user)))
+}
+
+async fn patch_user(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    Json(patch): Json<UserPatch>,
+) -> Result<Json<User>, AppError> {
+    patch.validate()?;
+    let hash = patch
+        .password
+        .as_deref()
+        .map(auth::hash_password)
+        .transpose()?;
+
+    let mut tx = state.pool.begin().await?;
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET password_hash = COALESCE($1, password_hash), role = COALESCE($2, role), updated_at = now()
+         WHERE id = $3 RETURNING id, username, role, created_at, updated_at",
+    )
+    .bind(&hash)
+    .bind(&patch.role)
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::not_found("User", id))?;
+
+    // A new password logs the user out everywhere
+    if hash.is_some() {
+        sqlx::query("DELETE FROM sessions WHERE user_id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(Json(user))
+}
+
+async fn delete_user(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    current: CurrentUser,
+) -> Result<StatusCode, AppError> {
+    if id == current.id {
+        return Err(AppError::bad_request("You cannot delete your own account"));
+    }
+
+    // Sessions and API tokens go with the user through ON DELETE CASCADE
+    let result = sqlx::query("DELETE FROM users WHERE id = $1")
+        .bind(id)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("User", id));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_tokens(
+    State(state): State<AppState>,
+    current: CurrentUser,
+) -> Result<Json<Vec<ApiToken>>, AppError> {
+    let tokens = sqlx::query_as::<_, ApiToken>(
+        "SELECT id, name, created_at, last_used_at, revoked_at FROM api_tokens WHERE user_id = $1 ORDER BY id",
+    )
+    .bind(current.id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(tokens))
+}
+
+async fn create_token(
+    State(state): State<AppState>,
+    current: CurrentUser,
+    Json(input): Json<NewApiToken>,
+) -> Result<(StatusCode, Json<CreatedApiToken>), AppError> {
+    input.validate()?;
+
+    let secret = auth::new_token();
+    let token = sqlx::query_as::<_, ApiToken>(
+        "INSERT INTO api_tokens (user_id, name, token_hash) VALUES ($1, $2, $3)
+         RETURNING id, name, created_at, last_used_at, revoked_at",
+    )
+    .bind(current.id)
+    .bind(input.name.trim())
+    .bind(auth::token_hash(&secret))
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(CreatedApiToken { token, secret })))
+}
+
+async fn revoke_token(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    current: CurrentUser,
+) -> Result<StatusCode, AppError> {
+    let result = sqlx::query(
+        "UPDATE api_tokens SET revoked_at = now() WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL",
+    )
+    .bind(id)
+    .bind(current.id)
+    .execute(&state.pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("API token", id));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Published articles only; the table is kept in step with the files at startup
+/// and by `POST /articles/sync`.
+async fn list_articles(State(state): State<AppState>) -> Result<Html<String>, AppError> {
+    let rows: Vec<(Article, Option<String>)> = sqlx::query_as::<_, ArticleWithEntrega>(
+        "SELECT a.id, a.slug, a.filename, a.title, a.author, a.status, a.published_at, a.entrega_id, a.created_at, a.updated_at, e.name AS entrega_name
+         FROM articles a LEFT JOIN entregas e ON e.id = a.entrega_id
+         WHERE a.status = 'published'
+         ORDER BY a.entrega_id NULLS LAST, a.published_at, a.slug",
+    )
+    .fetch_all(&state.pool)
+    .await?
+    .into_iter()
+    .map(|row| (row.article, row.entrega_name))
+    .collect();
+
+    let mut groups: Vec<minijinja::Value> = Vec::new();
+    let mut current: Option<(Option<i64>, String, Vec<minijinja::Value>)> = None;
+    for (article, entrega_name) in rows {
+        if current.as_ref().map(|(entrega_id, _, _)| *entrega_id) != Some(article.entrega_id) {
+            if let Some((entrega_id, heading, articles)) = current.take() {
+                groups.push(context! { entrega_id, heading, articles });
+            }
+            let heading = entrega_name.unwrap_or_else(|| "Sin entrega".to_string());
+            current = Some((article.entrega_id, heading, Vec::new()));
+        }
+
+        let mut meta = vec![article.status.clone()];
+        if let Some(author) = &article.author {
+            meta.push(author.clone());
+        }
+        if let Some(published_at) = article.published_at {
+            meta.push(published_at.format("%Y-%m-%d").to_string());
+        }
+        if let Some((_, _, articles)) = current.as_mut() {
+            articles.push(context! {
+                href => article_href(&article),
+                title => article.title,
+                meta => meta,
+            });
+        }
+    }
+    if let Some((entrega_id, heading, articles)) = current {
+        groups.push(context! { entrega_id, heading, articles });
+    }
+
+    templates::render("articles.html", context! { groups => groups })
+}
+
+fn article_href(article: &Article) -> String {
+    format!("/articles/{}", urlencoding::encode(&article.filename))
+}
+
+async fn sync_articles(
+    State(state): State<AppState>,
+    user: CurrentUser,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let synced = articles::sync_articles(&state.pool, &state.articles_dir, &user.username)
+        .await
+        .map_err(sync_error)?;
+
+    Ok(Json(
+        json!({ "status": "ok", "articles": synced.articles, "skipped": synced.skipped }),
+    ))
+}
+
+/// Drafts and archived articles are only listed for a logged-in caller.
+async fn get_entrega_articles(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+    user: Option<CurrentUser>,
+) -> Result<Json<Vec<Article>>, AppError> {
+    let exists: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM entregas WHERE id = $1 AND deleted_at IS NULL)",
+    )
+    .bind(id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    if !exists {
+        return Err(AppError::not_found("Entrega", id));
+    }
+
+    let articles = sqlx::query_as::<_, Article>(
+        "SELECT id, slug, filename, title, author, status, published_at, entrega_id, created_at, updated_at FROM articles WHERE entrega_id = $1 AND (status = 'published' OR $2) ORDER BY published_at, slug",
+    )
+    .bind(id)
+    .bind(user.is_some())
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(articles))
+}
+
+/// An article that is not published reads as missing unless the caller is logged in.
 async fn view_article(
     Path(filename): Path<String>,
     State(state): State<AppState>,
-) -> Result<Html<String>, StatusCode> {
+    user: Option<CurrentUser>,
+) -> Result<Html<String>, AppError> {
     if !is_safe_markdown_name(&filename) {
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(
+            AppError::bad_request("Articles are addressed by a plain .md file name")
+                .with_field("filename"),
+        );
     }
 
+    let not_found = || {
+        AppError::new(
+            StatusCode::NOT_FOUND,
+            "not_found",
+            format!("Article {filename} not found"),
+        )
+    };
     let file_path = state.articles_dir.join(&filename);
     let content = tokio::fs::read_to_string(file_path)
         .await
-        .map_err(|_| StatusCode::NOT_FOUND)?;
+        .map_err(|_| not_found())?;
+
+    let (front_matter, source) = articles::parse_front_matter(&content);
+    if front_matter.status() != "published" && user.is_none() {
+        return Err(not_found());
+    }
+    let slug = filename.trim_end_matches(".md");
+    let title = articles::article_title(&front_matter, source, slug);
 
-    let escaped = html_escape::encode_text(&content);
-    let body = format!(
-        "<h1>{}</h1><p><a href=\"/articles\">Back</a></p><pre>{}</pre>",
-        filename, escaped
-    );
+    let body = markdown::render_article(&state.pool, source).await?;
+    // Articles that open with their own `# ` heading already render a title
+    let show_heading = !source.lines().any(|line| line.starts_with("# "));
 
-    Ok(Html(body))
+    templates::render("article.html", context! { title, show_heading, body })
+}
+
+async fn entrega_page(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+) -> Result<Html<String>, AppError> {
+    let include = IncludeParams {
+        include: Some("tracks".to_string()),
+    };
+    let Tagged(entrega) = get_entrega(Path(id), State(state.clone()), Query(include)).await?;
+    let Json(articles) = get_entrega_articles(Path(id), State(state), None).await?;
+    let articles: Vec<_> = articles
+        .iter()
+        .map(|article| {
+            context! {
+                href => article_href(article),
+                title => &article.title,
+                author => &article.author,
+            }
+        })
+        .collect();
+
+    templates::render("entrega.html", context! { entrega, articles })
+}
+
+async fn album_page(
+    Path(id): Path<i64>,
+    State(state): State<AppState>,
+) -> Result<Html<String>, AppError> {
+    let include = IncludeParams {
+        include: Some("artist,tracks".to_string()),
+    };
+    let Tagged(album) = get_album(Path(id), State(state), Query(include)).await?;
+
+    templates::render("album.html", context! { album })
+}
+
+/// Relations requested through `?include=`, each checked against those the endpoint can embed.
+fn includes<'a>(params: &IncludeParams, allowed: &[&'a str]) -> Result<Vec<&'a str>, AppError> {
+    let Some(names) = &params.include else {
+        return Ok(Vec::new());
+    };
+
+    names
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            allowed
+                .iter()
+                .copied()
+                .find(|relation| *relation == name)
+                .ok_or_else(|| {
+                    AppError::bad_request(format!(
+                        "Unknown relation `{name}`, expected {}",
+                        allowed.join(", ")
+                    ))
+                    .with_field("include")
+                })
+        })
+        .collect()
+}
+
+/// Surface database failures from an article sync with their usual mapping.
+fn sync_error(error: Box<dyn std::error::Error + Send + Sync>) -> AppError {
+    match error.downcast::<sqlx::Error>() {
+        Ok(error) => AppError::from(*error),
+        Err(error) => AppError::internal(format!("Could not synchronize articles: {error}")),
+    }
 }
 
 fn is_safe_markdown_name(name: &str) -> bool {
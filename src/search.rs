@@ -0,0 +1,90 @@
+use html_escape::encode_text;
+use serde::Serialize;
+use sqlx::FromRow;
+
+use crate::db::DbPool;
+
+/// Every kind of record `/search` can return.
+pub const SEARCH_KINDS: [&str; 4] = ["track", "album", "artist", "article"];
+
+/// Private-use characters `ts_headline` wraps matches in, so the text around
+/// them can be escaped before they become `<b>` tags.
+const MATCH_START: char = '\u{E000}';
+const MATCH_END: char = '\u{E001}';
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct SearchHit {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub id: i64,
+    pub title: String,
+    pub snippet: String,
+    pub url: Option<String>,
+    pub rank: f32,
+}
+
+/// Ranked full-text search over tracks, albums, artists and articles.
+///
+/// The query is parsed with `websearch_to_tsquery` (quotes, `or`, `-word`) under both
+/// the Spanish and the simple configurations, matching the generated `search_vector`
+/// columns. Snippets are escaped HTML with matches in `<b>` tags. Only published
+/// articles are searched.
+pub async fn search(
+    pool: &DbPool,
+    query: &str,
+    kinds: &[String],
+    limit: i64,
+) -> Result<Vec<SearchHit>, sqlx::Error> {
+    let mut hits = sqlx::query_as::<_, SearchHit>(
+        "WITH q AS (
+             SELECT websearch_to_tsquery('spanish', $1) || websearch_to_tsquery('simple', $1) AS query,
+                    $4::text AS marks
+         )
+         SELECT kind, id, title, snippet, url, rank FROM (
+             SELECT 'track' AS kind, t.id, t.title,
+                    ts_headline('simple', t.artist_name || ' — ' || t.title, q.query, q.marks) AS snippet,
+                    NULL::text AS url,
+                    ts_rank(t.search_vector, q.query) AS rank
+             FROM tracks t, q WHERE t.deleted_at IS NULL AND t.search_vector @@ q.query
+             UNION ALL
+             SELECT 'album', a.id, a.title,
+                    ts_headline('simple', concat_ws(' · ', a.title, a.label, a.genre, a.style), q.query, q.marks),
+                    NULL::text,
+                    ts_rank(a.search_vector, q.query)
+             FROM albums a, q WHERE a.deleted_at IS NULL AND a.search_vector @@ q.query
+             UNION ALL
+             SELECT 'artist', ar.id, ar.name,
+                    ts_headline('simple', ar.name, q.query, q.marks),
+                    NULL::text,
+                    ts_rank(ar.search_vector, q.query)
+             FROM artists ar, q WHERE ar.deleted_at IS NULL AND ar.search_vector @@ q.query
+             UNION ALL
+             SELECT 'article', art.id, art.title,
+                    ts_headline('spanish', art.body, q.query, q.marks || ', MaxFragments=2, MaxWords=25, MinWords=8'),
+                    art.filename,
+                    ts_rank(art.search_vector, q.query)
+             FROM articles art, q WHERE art.status = 'published' AND art.search_vector @@ q.query
+         ) hits
+         WHERE kind = ANY($2)
+         ORDER BY rank DESC, kind, id
+         LIMIT $3",
+    )
+    .bind(query)
+    .bind(kinds)
+    .bind(limit)
+    .bind(format!("StartSel={MATCH_START}, StopSel={MATCH_END}"))
+    .fetch_all(pool)
+    .await?;
+
+    for hit in &mut hits {
+        hit.snippet = encode_text(&hit.snippet)
+            .replace(MATCH_START, "<b>")
+            .replace(MATCH_END, "</b>");
+        // Articles come back with their file name as the url
+        hit.url = hit
+            .url
+            .take()
+            .map(|filename| format!("/articles/{}", urlencoding::encode(&filename)));
+    }
+    Ok(hits)
+}
@@ -0,0 +1,284 @@
+use serde::Serialize;
+
+use crate::keys::Key;
+use crate::models::Track;
+
+/// Cost of one step around the Camelot wheel (or a major/minor switch).
+const KEY_STEP_COST: f64 = 4.0;
+/// Cost assumed for a transition where either key is unknown.
+const UNKNOWN_KEY_COST: f64 = 6.0;
+/// Cost per BPM of distance from the target curve.
+const TARGET_BPM_COST: f64 = 1.0;
+/// Cost per BPM of tempo jump from the previous track.
+const TEMPO_JUMP_COST: f64 = 1.5;
+/// Cost assumed for a transition where either BPM is unknown.
+const UNKNOWN_BPM_COST: f64 = 8.0;
+
+#[derive(Debug, Serialize)]
+pub struct Transition {
+    pub from_track_id: i64,
+    pub key_relation: &'static str, // same_key, relative, adjacent, clash or unknown
+    pub key_steps: Option<u8>,
+    pub bpm_change: Option<f64>,
+    pub cost: f64,
+    pub explanation: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SetlistSlot {
+    pub position: usize,
+    pub target_bpm: Option<f64>,
+    pub track: Track,
+    pub transition: Option<Transition>, // None for the opening track
+}
+
+/// Order `tracks` into a set of `length` tracks starting from `start_id`.
+///
+/// Greedy construction: each slot takes the remaining track with the cheapest
+/// transition from the previous one, weighing key distance on the Camelot wheel,
+/// the tempo jump, and the distance from the target BPM curve. `bpm_curve` points
+/// are spread evenly across the set and linearly interpolated between them.
+///
+/// Returns `None` when `start_id` is not part of the pool.
+pub fn generate(
+    mut tracks: Vec<Track>,
+    start_id: Option<i64>,
+    bpm_curve: &[f64],
+    length: usize,
+) -> Option<Vec<SetlistSlot>> {
+    let length = length.min(tracks.len());
+    if length == 0 {
+        return Some(Vec::new());
+    }
+
+    let first_target = target_bpm(bpm_curve, 0, length);
+    let start_index = match start_id {
+        Some(id) => tracks.iter().position(|track| track.id == id)?,
+        // Without an explicit opener, take the track closest to the start of the curve
+        None => (0..tracks.len())
+            .min_by(|&a, &b| {
+                let a_cost = target_cost(&tracks[a], first_target);
+                let b_cost = target_cost(&tracks[b], first_target);
+                a_cost.total_cmp(&b_cost)
+            })
+            .unwrap_or(0),
+    };
+
+    let mut slots = vec![SetlistSlot {
+        position: 1,
+        target_bpm: first_target,
+        track: tracks.swap_remove(start_index),
+        transition: None,
+    }];
+
+    for index in 1..length {
+        let target = target_bpm(bpm_curve, index, length);
+        let previous = &slots[index - 1].track;
+        let next_index = (0..tracks.len()).min_by(|&a, &b| {
+            let a_cost = transition(previous, &tracks[a], target).cost;
+            let b_cost = transition(previous, &tracks[b], target).cost;
+            a_cost.total_cmp(&b_cost)
+        })?;
+
+        let track = tracks.swap_remove(next_index);
+        let transition = transition(previous, &track, target);
+        slots.push(SetlistSlot {
+            position: index + 1,
+            target_bpm: target,
+            track,
+            transition: Some(transition),
+        });
+    }
+
+    Some(slots)
+}
+
+/// Target BPM for slot `index` out of `length`, or `None` without a curve.
+fn target_bpm(curve: &[f64], index: usize, length: usize) -> Option<f64> {
+    match curve {
+        [] => None,
+        [bpm] => Some(*bpm),
+        _ => {
+            let progress = if length > 1 {
+                index as f64 / (length - 1) as f64
+            } else {
+                0.0
+            };
+            let scaled = progress * (curve.len() - 1) as f64;
+            let lower = (scaled.floor() as usize).min(curve.len() - 2);
+            let fraction = scaled - lower as f64;
+            Some(curve[lower] + (curve[lower + 1] - curve[lower]) * fraction)
+        }
+    }
+}
+
+fn target_cost(track: &Track, target: Option<f64>) -> f64 {
+    match (track.bpm, target) {
+        (Some(bpm), Some(target)) => (bpm - target).abs() * TARGET_BPM_COST,
+        (None, Some(_)) => UNKNOWN_BPM_COST,
+        (_, None) => 0.0,
+    }
+}
+
+fn transition(from: &Track, to: &Track, target: Option<f64>) -> Transition {
+    let from_key = from.tone.as_deref().and_then(Key::parse);
+    let to_key = to.tone.as_deref().and_then(Key::parse);
+    let mut explanation = Vec::new();
+
+    let (key_relation, key_steps, key_cost) = match from_key.zip(to_key) {
+        Some((from_key, to_key)) => {
+            let steps = from_key.distance(to_key);
+            let relation = from_key.relation(to_key).map_or("clash", |r| r.as_str());
+            explanation.push(format!(
+                "{} → {}: {} ({} step{})",
+                from_key.camelot(),
+                to_key.camelot(),
+                relation.replace('_', " "),
+                steps,
+                if steps == 1 { "" } else { "s" }
+            ));
+            (relation, Some(steps), f64::from(steps) * KEY_STEP_COST)
+        }
+        None => {
+            explanation.push("key unknown".to_string());
+            ("unknown", None, UNKNOWN_KEY_COST)
+        }
+    };
+
+    let (bpm_change, tempo_cost) = match from.bpm.zip(to.bpm) {
+        Some((from_bpm, to_bpm)) => {
+            let change = ((to_bpm - from_bpm) * 100.0).round() / 100.0;
+            explanation.push(format!("{from_bpm:.2} → {to_bpm:.2} BPM ({change:+.2})"));
+            (Some(change), change.abs() * TEMPO_JUMP_COST)
+        }
+        None => {
+            explanation.push("BPM unknown".to_string());
+            (None, UNKNOWN_BPM_COST)
+        }
+    };
+
+    if let Some(target) = target {
+        explanation.push(format!("target {target:.1} BPM"));
+    }
+
+    let cost = key_cost + tempo_cost + target_cost(to, target);
+    Transition {
+        from_track_id: from.id,
+        key_relation,
+        key_steps,
+        bpm_change,
+        cost: (cost * 100.0).round() / 100.0,
+        explanation: explanation.join("; "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    fn track(id: i64, tone: Option<&str>, bpm: Option<f64>) -> Track {
+        Track {
+            id,
+            title: format!("Track {id}"),
+            artist_name: "Artist".to_string(),
+            album_id: None,
+            duration_seconds: None,
+            bpm,
+            tone: tone.map(str::to_string),
+            position: None,
+            score: None,
+            entrega_id: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            version: 1,
+            camelot: None,
+            open_key: None,
+        }
+    }
+
+    fn pool() -> Vec<Track> {
+        vec![
+            track(1, Some("C"), Some(120.0)),
+            track(2, Some("C"), Some(140.0)),
+            track(3, Some("F#"), Some(121.0)),
+            track(4, Some("G"), Some(121.0)),
+        ]
+    }
+
+    fn order(slots: &[SetlistSlot]) -> Vec<i64> {
+        slots.iter().map(|slot| slot.track.id).collect()
+    }
+
+    #[test]
+    fn target_curve_is_interpolated_across_the_set() {
+        assert_eq!(target_bpm(&[], 2, 5), None);
+        assert_eq!(target_bpm(&[124.0], 3, 5), Some(124.0));
+
+        let rise: Vec<_> = (0..3).map(|i| target_bpm(&[120.0, 130.0], i, 3)).collect();
+        assert_eq!(rise, [Some(120.0), Some(125.0), Some(130.0)]);
+
+        let peak: Vec<_> = (0..5)
+            .map(|i| target_bpm(&[120.0, 130.0, 120.0], i, 5))
+            .collect();
+        assert_eq!(
+            peak,
+            [
+                Some(120.0),
+                Some(125.0),
+                Some(130.0),
+                Some(125.0),
+                Some(120.0)
+            ]
+        );
+        assert_eq!(target_bpm(&[120.0, 130.0], 0, 1), Some(120.0));
+    }
+
+    #[test]
+    fn each_slot_takes_the_cheapest_transition() {
+        let slots = generate(pool(), Some(1), &[], 4).unwrap();
+        // G is one step from C and one BPM up; F# is six steps away and 140 BPM a big jump
+        assert_eq!(order(&slots), [1, 4, 3, 2]);
+
+        assert!(slots[0].transition.is_none());
+        let second = slots[1].transition.as_ref().unwrap();
+        assert_eq!(second.from_track_id, 1);
+        assert_eq!(second.key_relation, "adjacent");
+        assert_eq!(second.key_steps, Some(1));
+        assert_eq!(second.bpm_change, Some(1.0));
+        assert_eq!(second.cost, KEY_STEP_COST + TEMPO_JUMP_COST);
+        assert_eq!(slots[2].transition.as_ref().unwrap().key_relation, "clash");
+    }
+
+    #[test]
+    fn opens_with_the_track_closest_to_the_curve() {
+        let slots = generate(pool(), None, &[140.0, 120.0], 2).unwrap();
+        assert_eq!(slots[0].track.id, 2);
+        assert_eq!(slots[0].target_bpm, Some(140.0));
+        assert_eq!(slots[1].target_bpm, Some(120.0));
+    }
+
+    #[test]
+    fn unknown_keys_and_tempos_cost_a_fixed_penalty() {
+        let slots = generate(
+            vec![track(1, None, None), track(2, Some("C"), Some(120.0))],
+            Some(1),
+            &[],
+            2,
+        )
+        .unwrap();
+        let transition = slots[1].transition.as_ref().unwrap();
+        assert_eq!(transition.key_relation, "unknown");
+        assert_eq!(transition.key_steps, None);
+        assert_eq!(transition.cost, UNKNOWN_KEY_COST + UNKNOWN_BPM_COST);
+    }
+
+    #[test]
+    fn length_is_capped_by_the_pool() {
+        assert_eq!(generate(pool(), Some(1), &[], 10).unwrap().len(), 4);
+        assert_eq!(generate(pool(), Some(1), &[], 2).unwrap().len(), 2);
+        assert!(generate(Vec::new(), None, &[], 5).unwrap().is_empty());
+        assert!(generate(pool(), Some(99), &[], 3).is_none());
+    }
+}
@@ -0,0 +1,391 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::types::Json;
+use sqlx::PgConnection;
+
+use crate::db::DbPool;
+use crate::trash::Kind;
+
+/// Layout of the snapshot document, bumped when it changes incompatibly.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Tables left out: the migration history belongs to the schema and sessions are
+/// short-lived logins.
+const SKIPPED: &[&str] = &["_sqlx_migrations", "sessions"];
+
+/// Accounts and their password and token hashes, only written when asked for so
+/// snapshots can be kept in git.
+const ACCOUNTS: &[&str] = &["users", "api_tokens"];
+
+/// Foreign keys `/import/json` reads as payload positions, also written as the
+/// referenced record's name so a snapshot can be imported into a live database.
+const NAMED_REFERENCES: &[(&str, &str, &str, Kind)] = &[
+    ("albums", "artist_id", "artist", Kind::Artist),
+    ("entregas", "batch_id", "batch", Kind::Batch),
+    ("tracks", "album_id", "album", Kind::Album),
+    ("tracks", "entrega_id", "entrega", Kind::Entrega),
+];
+
+/// Every row of every table, keyed by table name next to the version fields.
+///
+/// The `batches`, `entregas`, `albums` and `tracks` lists double as an
+/// [`ImportPayload`](crate::models::ImportPayload), which skips their trashed rows;
+/// only [`restore`] brings back the trash, ids and history as they were.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub snapshot_version: u32,
+    pub schema_version: i64, // Latest migration applied to the database it was taken from
+    pub exported_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub tables: BTreeMap<String, Vec<Map<String, Value>>>,
+}
+
+/// A table as the catalog describes it.
+struct Table {
+    name: String,
+    columns: Vec<String>,   // Writable columns, in table order
+    generated: Vec<String>, // Derived columns such as search_vector, never exported
+    key: Vec<String>,       // Primary key, which orders the rows
+    serial: Vec<String>,    // Columns backed by a sequence
+    references: Vec<String>,
+}
+
+/// Read every table in one consistent view, the [`ACCOUNTS`] tables only with
+/// `include_accounts`.
+pub async fn take(pool: &DbPool, include_accounts: bool) -> Result<Snapshot, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ READ ONLY")
+        .execute(&mut *tx)
+        .await?;
+
+    let mut snapshot = Snapshot {
+        snapshot_version: SNAPSHOT_VERSION,
+        schema_version: schema_version(&mut tx).await?,
+        exported_at: Utc::now(),
+        tables: BTreeMap::new(),
+    };
+    for table in tables(&mut tx).await? {
+        if !include_accounts && ACCOUNTS.contains(&table.name.as_str()) {
+            continue;
+        }
+        let order = match table.key.as_slice() {
+            [] => String::new(),
+            key => format!(" ORDER BY {}", idents(key)),
+        };
+        let Json(rows): Json<Vec<Map<String, Value>>> = sqlx::query_scalar(&format!(
+            "SELECT COALESCE(jsonb_agg(to_jsonb(t) - $1::text[]{order}), '[]') FROM {} t",
+            ident(&table.name)
+        ))
+        .bind(&table.generated)
+        .fetch_one(&mut *tx)
+        .await?;
+        snapshot.tables.insert(table.name, rows);
+    }
+    tx.commit().await?;
+
+    name_references(&mut snapshot.tables);
+    Ok(snapshot)
+}
+
+/// Add `"album": "<title>"` next to `album_id` and so on, and the album's artist
+/// as a track's `"album_artist"`.
+fn name_references(tables: &mut BTreeMap<String, Vec<Map<String, Value>>>) {
+    for (table, column, name, kind) in NAMED_REFERENCES {
+        let labels: HashMap<i64, Value> = tables
+            .get(kind.table())
+            .into_iter()
+            .flatten()
+            .filter_map(|row| {
+                Some((
+                    row.get("id")?.as_i64()?,
+                    row.get(kind.label_column())?.clone(),
+                ))
+            })
+            .collect();
+
+        for row in tables.get_mut(*table).into_iter().flatten() {
+            let label = row
+                .get(*column)
+                .and_then(Value::as_i64)
+                .and_then(|id| labels.get(&id));
+            if let Some(label) = label {
+                row.insert(name.to_string(), label.clone());
+            }
+        }
+    }
+
+    // Albums sharing a title are told apart by their artist, named above
+    let album_artists: HashMap<i64, Value> = tables
+        .get("albums")
+        .into_iter()
+        .flatten()
+        .filter_map(|album| Some((album.get("id")?.as_i64()?, album.get("artist")?.clone())))
+        .collect();
+    for track in tables.get_mut("tracks").into_iter().flatten() {
+        let artist = track
+            .get("album_id")
+            .and_then(Value::as_i64)
+            .and_then(|id| album_artists.get(&id));
+        if let Some(artist) = artist {
+            track.insert("album_artist".to_string(), artist.clone());
+        }
+    }
+}
+
+/// Rows restored per table, in the order they were written.
+pub type Restored = Vec<(String, usize)>;
+
+/// Load a snapshot into a database that has been migrated but holds no data in
+/// the snapshot's tables.
+///
+/// Rows keep their ids, the audit and version triggers are held off so the
+/// history comes back as it was, and sequences continue after the highest id.
+pub async fn restore(
+    pool: &DbPool,
+    snapshot: &Snapshot,
+) -> Result<Restored, Box<dyn std::error::Error>> {
+    if snapshot.snapshot_version != SNAPSHOT_VERSION {
+        return Err(format!(
+            "snapshot_version {} is not supported, this build reads version {SNAPSHOT_VERSION}",
+            snapshot.snapshot_version
+        )
+        .into());
+    }
+
+    let mut tx = pool.begin().await?;
+    let schema = schema_version(&mut tx).await?;
+    if snapshot.schema_version > schema {
+        return Err(format!(
+            "The snapshot was taken at migration {}, newer than this database ({schema}); update the code first",
+            snapshot.schema_version
+        )
+        .into());
+    }
+
+    let tables = tables(&mut tx).await?;
+    if let Some(unknown) = snapshot
+        .tables
+        .keys()
+        .find(|name| !tables.iter().any(|table| &table.name == *name))
+    {
+        return Err(format!("The snapshot has a table `{unknown}` this database does not").into());
+    }
+    // Tables the snapshot leaves out, such as the accounts, may already have rows
+    for table in tables
+        .iter()
+        .filter(|table| snapshot.tables.contains_key(&table.name))
+    {
+        let occupied: bool = sqlx::query_scalar(&format!(
+            "SELECT EXISTS (SELECT 1 FROM {})",
+            ident(&table.name)
+        ))
+        .fetch_one(&mut *tx)
+        .await?;
+        if occupied {
+            return Err(format!(
+                "Restoring needs an empty database, but `{}` has rows (reset it with `make resetDb`)",
+                table.name
+            )
+            .into());
+        }
+    }
+
+    let mut restored = Restored::new();
+    for table in &tables {
+        let Some(rows) = snapshot
+            .tables
+            .get(&table.name)
+            .filter(|rows| !rows.is_empty())
+        else {
+            continue;
+        };
+
+        // Columns added by later migrations keep their defaults
+        let columns: Vec<String> = table
+            .columns
+            .iter()
+            .filter(|column| rows.iter().any(|row| row.contains_key(*column)))
+            .cloned()
+            .collect();
+        let name = ident(&table.name);
+        sqlx::query(&format!("ALTER TABLE {name} DISABLE TRIGGER USER"))
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(&format!(
+            "INSERT INTO {name} ({columns}) OVERRIDING SYSTEM VALUE SELECT {columns} FROM jsonb_populate_recordset(NULL::{name}, $1)",
+            columns = idents(&columns)
+        ))
+        .bind(Json(rows))
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(&format!("ALTER TABLE {name} ENABLE TRIGGER USER"))
+            .execute(&mut *tx)
+            .await?;
+
+        for column in &table.serial {
+            sqlx::query(&format!(
+                "SELECT setval(pg_get_serial_sequence($1, $2), COALESCE(MAX({}), 0) + 1, false) FROM {name}",
+                ident(column)
+            ))
+            .bind(&name)
+            .bind(column)
+            .execute(&mut *tx)
+            .await?;
+        }
+        restored.push((table.name.clone(), rows.len()));
+    }
+    tx.commit().await?;
+
+    Ok(restored)
+}
+
+async fn schema_version(conn: &mut PgConnection) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("SELECT COALESCE(MAX(version), 0) FROM _sqlx_migrations WHERE success")
+        .fetch_one(conn)
+        .await
+}
+
+/// The tables of the current schema, the first on the `search_path`, each after the
+/// tables it references.
+async fn tables(conn: &mut PgConnection) -> Result<Vec<Table>, sqlx::Error> {
+    let names: Vec<String> = sqlx::query_scalar(
+        "SELECT table_name::text FROM information_schema.tables WHERE table_schema = current_schema() AND table_type = 'BASE TABLE' AND NOT table_name = ANY($1) ORDER BY table_name",
+    )
+    .bind(SKIPPED)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let columns: Vec<(String, String, bool, bool)> = sqlx::query_as(
+        "SELECT table_name::text, column_name::text, is_generated = 'ALWAYS', pg_get_serial_sequence(quote_ident(table_schema) || '.' || quote_ident(table_name), column_name) IS NOT NULL
+         FROM information_schema.columns WHERE table_schema = current_schema() ORDER BY table_name, ordinal_position",
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let keys: Vec<(String, String)> = sqlx::query_as(
+        "SELECT c.conrelid::regclass::text, a.attname::text
+         FROM pg_constraint c
+         CROSS JOIN LATERAL unnest(c.conkey) WITH ORDINALITY AS k(attnum, n)
+         JOIN pg_attribute a ON a.attrelid = c.conrelid AND a.attnum = k.attnum
+         WHERE c.contype = 'p' AND c.connamespace = current_schema()::regnamespace
+         ORDER BY 1, k.n",
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let references: Vec<(String, String)> = sqlx::query_as(
+        "SELECT conrelid::regclass::text, confrelid::regclass::text FROM pg_constraint
+         WHERE contype = 'f' AND connamespace = current_schema()::regnamespace AND conrelid <> confrelid",
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut pending: Vec<Table> = names
+        .into_iter()
+        .map(|name| {
+            let of_table = |(table, _): &&(String, String)| *table == name;
+            Table {
+                columns: columns
+                    .iter()
+                    .filter(|(table, _, generated, _)| *table == name && !generated)
+                    .map(|(_, column, _, _)| column.clone())
+                    .collect(),
+                generated: columns
+                    .iter()
+                    .filter(|(table, _, generated, _)| *table == name && *generated)
+                    .map(|(_, column, _, _)| column.clone())
+                    .collect(),
+                serial: columns
+                    .iter()
+                    .filter(|(table, _, _, serial)| *table == name && *serial)
+                    .map(|(_, column, _, _)| column.clone())
+                    .collect(),
+                key: keys
+                    .iter()
+                    .filter(of_table)
+                    .map(|(_, column)| column.clone())
+                    .collect(),
+                references: references
+                    .iter()
+                    .filter(of_table)
+                    .map(|(_, referenced)| referenced.clone())
+                    .collect(),
+                name,
+            }
+        })
+        .collect();
+
+    // Take the first table whose references are all placed; a cycle keeps the rest as is
+    let mut ordered: Vec<Table> = Vec::with_capacity(pending.len());
+    while !pending.is_empty() {
+        let ready = pending
+            .iter()
+            .position(|table| {
+                table.references.iter().all(|referenced| {
+                    ordered.iter().any(|placed| &placed.name == referenced)
+                        || !pending.iter().any(|other| &other.name == referenced)
+                })
+            })
+            .unwrap_or(0);
+        ordered.push(pending.remove(ready));
+    }
+
+    Ok(ordered)
+}
+
+fn ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn idents(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| ident(name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn rows(value: Value) -> Vec<Map<String, Value>> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn references_are_named_for_the_import() {
+        let mut tables = BTreeMap::from([
+            (
+                "artists".to_string(),
+                rows(json!([{ "id": 1, "name": "Fred Fresh" }, { "id": 2, "name": "DJ Sneak" }])),
+            ),
+            (
+                "albums".to_string(),
+                rows(json!([
+                    { "id": 4, "title": "Neighbors", "artist_id": 1 },
+                    { "id": 5, "title": "Neighbors", "artist_id": 2 }
+                ])),
+            ),
+            (
+                "tracks".to_string(),
+                rows(json!([{ "id": 9, "title": "Work", "album_id": 5, "entrega_id": null }])),
+            ),
+        ]);
+        name_references(&mut tables);
+
+        assert_eq!(tables["albums"][1]["artist"], "DJ Sneak");
+        assert_eq!(
+            Value::Object(tables["tracks"][0].clone()),
+            json!({
+                "id": 9, "title": "Work", "album_id": 5, "entrega_id": null,
+                "album": "Neighbors", "album_artist": "DJ Sneak"
+            })
+        );
+    }
+}
@@ -0,0 +1,672 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use axum::extract::Query;
+use axum::http::Uri;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use sqlx::{FromRow, Postgres, QueryBuilder};
+
+use crate::db::DbPool;
+use crate::error::AppError;
+use crate::listing;
+use crate::models::{
+    Album, AlbumFilter, Artist, ArtistFilter, Batch, BatchFilter, Entrega, EntregaFilter,
+    ImportItem, ImportPayload, ImportRefs, NewAlbum, NewTrack, Track, TrackFilter,
+};
+use crate::trash::{Kind, KINDS};
+use crate::validate::{FieldError, Validate};
+
+/// Importable columns and the header spellings accepted for each, after
+/// lowercasing and turning spaces and dashes into underscores.
+const TRACK_COLUMNS: &[(&str, &[&str])] = &[
+    ("title", &["title", "track", "track_title"]),
+    ("artist", &["artist", "artist_name"]),
+    ("album", &["album", "album_title"]),
+    ("album_artist", &["album_artist"]),
+    ("entrega", &["entrega", "entrega_name"]),
+    ("position", &["position", "pos"]),
+    ("duration_seconds", &["duration_seconds", "duration"]),
+    ("bpm", &["bpm", "tempo"]),
+    ("tone", &["tone", "key"]),
+    ("score", &["score"]),
+    ("created_at", &["created_at"]),
+];
+
+const ALBUM_COLUMNS: &[(&str, &[&str])] = &[
+    ("title", &["title", "album", "album_title"]),
+    ("artist", &["artist", "artist_name"]),
+    ("release_year", &["release_year", "year"]),
+    ("label", &["label"]),
+    ("format", &["format"]),
+    ("country", &["country"]),
+    ("genre", &["genre"]),
+    ("style", &["style"]),
+    ("created_at", &["created_at"]),
+];
+
+/// Columns written by `/export/csv` that an import ignores.
+const EXPORT_ONLY: &[&str] = &["id", "updated_at"];
+
+const TRACK_EXPORT: &[&str] = &[
+    "id",
+    "title",
+    "artist",
+    "album",
+    "album_artist",
+    "entrega",
+    "position",
+    "duration_seconds",
+    "bpm",
+    "tone",
+    "score",
+    "created_at",
+    "updated_at",
+];
+
+const ALBUM_EXPORT: &[&str] = &[
+    "id",
+    "title",
+    "artist",
+    "release_year",
+    "label",
+    "format",
+    "country",
+    "genre",
+    "style",
+    "created_at",
+    "updated_at",
+];
+
+const ARTIST_EXPORT: &[&str] = &["id", "name", "country", "created_at", "updated_at"];
+const BATCH_EXPORT: &[&str] = &["id", "name", "created_at", "updated_at"];
+const ENTREGA_EXPORT: &[&str] = &["id", "name", "batch", "created_at", "updated_at"];
+
+/// Resolve `?type=`, the table name of the records in the file.
+pub fn kind(name: &str) -> Result<Kind, AppError> {
+    Kind::from_table(name).ok_or_else(|| {
+        let names: Vec<&str> = KINDS.iter().map(|kind| kind.table()).collect();
+        AppError::bad_request(format!("type must be one of {}", names.join(", ")))
+            .with_field("type")
+    })
+}
+
+/// Turn a tracks or albums CSV into an import payload.
+///
+/// Albums, entregas and artists are referenced by name, which the importer resolves
+/// against the database. `map` renames headers the usual spellings don't cover, as
+/// `Header:column` pairs separated by commas.
+pub fn parse(kind: Kind, body: &[u8], map: Option<&str>) -> Result<ImportPayload, AppError> {
+    let columns = match kind {
+        Kind::Track => TRACK_COLUMNS,
+        Kind::Album => ALBUM_COLUMNS,
+        _ => {
+            return Err(
+                AppError::bad_request("CSV imports take tracks or albums").with_field("type")
+            )
+        }
+    };
+    let text = std::str::from_utf8(body)
+        .map_err(|_| AppError::bad_request("The CSV must be UTF-8 encoded"))?;
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+
+    let renames = renames(map.unwrap_or_default(), columns)?;
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter(text))
+        .trim(csv::Trim::All)
+        .from_reader(text.as_bytes());
+    let headers = reader.headers().map_err(invalid_csv)?.clone();
+    let header_columns = header_columns(&headers, columns, &renames)?;
+
+    let list = kind.table();
+    let mut tracks = Vec::new();
+    let mut albums = Vec::new();
+    let mut errors = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let record = record.map_err(invalid_csv)?;
+        let mut row = Row {
+            prefix: format!("{list}[{index}]"),
+            values: header_columns
+                .iter()
+                .zip(record.iter())
+                .filter_map(|(column, value)| Some(((*column)?, value.to_string())))
+                .filter(|(_, value)| !value.is_empty())
+                .collect(),
+            errors: Vec::new(),
+        };
+        match kind {
+            Kind::Track => tracks.push(track(&mut row)),
+            _ => albums.push(album(&mut row)),
+        }
+        errors.append(&mut row.errors);
+    }
+    if !errors.is_empty() {
+        return Err(errors.into());
+    }
+
+    let payload = ImportPayload {
+        albums: (kind == Kind::Album).then_some(albums),
+        tracks: (kind == Kind::Track).then_some(tracks),
+        batches: None,
+        entregas: None,
+    };
+    payload.validate()?;
+
+    Ok(payload)
+}
+
+/// The non-empty cells of one CSV row by column, and what failed to parse.
+struct Row {
+    prefix: String,
+    values: HashMap<&'static str, String>,
+    errors: Vec<FieldError>,
+}
+
+impl Row {
+    fn text(&self, column: &str) -> Option<String> {
+        self.values.get(column).cloned()
+    }
+
+    fn parse<T: FromStr>(&mut self, column: &str, expected: &str) -> Option<T> {
+        let value = self.values.get(column)?.clone();
+        let parsed = value.parse().ok();
+        if parsed.is_none() {
+            self.fail(column, format!("must be {expected}, got `{value}`"));
+        }
+        parsed
+    }
+
+    /// A decimal that may use a comma, as spreadsheets in many locales write it.
+    fn decimal(&mut self, column: &str) -> Option<f64> {
+        let value = self.values.get(column)?.clone();
+        let parsed = value.replace(',', ".").parse().ok();
+        if parsed.is_none() {
+            self.fail(column, format!("must be a number, got `{value}`"));
+        }
+        parsed
+    }
+
+    /// Seconds, or `m:ss` / `h:mm:ss`.
+    fn duration(&mut self, column: &str) -> Option<i64> {
+        let value = self.values.get(column)?.clone();
+        let parsed = value.split(':').try_fold(0i64, |total, part| {
+            let part: i64 = part.parse().ok()?;
+            total.checked_mul(60)?.checked_add(part)
+        });
+        if parsed.is_none() {
+            self.fail(column, format!("must be seconds or m:ss, got `{value}`"));
+        }
+        parsed
+    }
+
+    fn fail(&mut self, column: &str, message: String) {
+        self.errors.push(FieldError {
+            field: format!("{}.{column}", self.prefix),
+            message,
+        });
+    }
+}
+
+fn track(row: &mut Row) -> ImportItem<NewTrack> {
+    ImportItem {
+        record: NewTrack {
+            title: row.text("title").unwrap_or_default(),
+            artist_name: row.text("artist").unwrap_or_default(),
+            album_id: None,
+            duration_seconds: row.duration("duration_seconds"),
+            bpm: row.decimal("bpm"),
+            tone: row.text("tone"),
+            position: row.text("position"),
+            score: row.text("score"),
+            entrega_id: None,
+            created_at: row.parse::<DateTime<Utc>>("created_at", "an RFC 3339 timestamp"),
+        },
+        refs: ImportRefs {
+            album: row.text("album"),
+            album_artist: row.text("album_artist"),
+            entrega: row.text("entrega"),
+            ..ImportRefs::default()
+        },
+        deleted_at: None,
+    }
+}
+
+fn album(row: &mut Row) -> ImportItem<NewAlbum> {
+    ImportItem {
+        record: NewAlbum {
+            title: row.text("title").unwrap_or_default(),
+            artist_id: None,
+            release_year: row.parse("release_year", "a year"),
+            label: row.text("label"),
+            format: row.text("format"),
+            country: row.text("country"),
+            genre: row.text("genre"),
+            style: row.text("style"),
+            created_at: row.parse::<DateTime<Utc>>("created_at", "an RFC 3339 timestamp"),
+        },
+        refs: ImportRefs {
+            artist: row.text("artist"),
+            ..ImportRefs::default()
+        },
+        deleted_at: None,
+    }
+}
+
+/// Lowercase a header and turn spaces and dashes into underscores.
+fn normalize_header(header: &str) -> String {
+    header
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c == ' ' || c == '-' { '_' } else { c })
+        .collect()
+}
+
+fn column_for(header: &str, columns: &[(&'static str, &[&str])]) -> Option<&'static str> {
+    columns
+        .iter()
+        .find(|(_, aliases)| aliases.contains(&header))
+        .map(|(column, _)| *column)
+}
+
+/// Parse `?map=Artista:artist,Tono:tone` into normalized header → column.
+fn renames(
+    map: &str,
+    columns: &[(&'static str, &[&str])],
+) -> Result<HashMap<String, &'static str>, AppError> {
+    map.split(',')
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| {
+            let (header, target) = pair.split_once(':').ok_or_else(|| {
+                AppError::bad_request(format!("`{pair}` is not a Header:column pair"))
+                    .with_field("map")
+            })?;
+            let column = column_for(&normalize_header(target), columns).ok_or_else(|| {
+                AppError::bad_request(format!(
+                    "`{}` is not an importable column, expected one of {}",
+                    target.trim(),
+                    column_names(columns)
+                ))
+                .with_field("map")
+            })?;
+            Ok((normalize_header(header), column))
+        })
+        .collect()
+}
+
+/// The column of every header, `None` for the export-only ones.
+fn header_columns(
+    headers: &csv::StringRecord,
+    columns: &[(&'static str, &[&str])],
+    renames: &HashMap<String, &'static str>,
+) -> Result<Vec<Option<&'static str>>, AppError> {
+    let mut resolved: Vec<Option<&'static str>> = Vec::new();
+    for header in headers {
+        let normalized = normalize_header(header);
+        let column = match renames.get(&normalized) {
+            Some(column) => Some(*column),
+            None if EXPORT_ONLY.contains(&normalized.as_str()) => None,
+            None => Some(column_for(&normalized, columns).ok_or_else(|| {
+                AppError::bad_request(format!(
+                    "Unknown column `{header}`, expected {} (or rename it with ?map=)",
+                    column_names(columns)
+                ))
+            })?),
+        };
+        if column.is_some() && resolved.contains(&column) {
+            return Err(AppError::bad_request(format!(
+                "Column `{header}` appears twice"
+            )));
+        }
+        resolved.push(column);
+    }
+
+    if !resolved.contains(&Some("title")) {
+        return Err(AppError::bad_request("The CSV needs a title column"));
+    }
+
+    Ok(resolved)
+}
+
+fn column_names(columns: &[(&str, &[&str])]) -> String {
+    let names: Vec<&str> = columns.iter().map(|(column, _)| *column).collect();
+    names.join(", ")
+}
+
+/// Pick comma, semicolon or tab, whichever the header line uses most.
+fn delimiter(text: &str) -> u8 {
+    let header = text.lines().next().unwrap_or_default();
+    [b',', b';', b'\t']
+        .into_iter()
+        .max_by_key(|delimiter| header.bytes().filter(|byte| byte == delimiter).count())
+        .filter(|delimiter| header.as_bytes().contains(delimiter))
+        .unwrap_or(b',')
+}
+
+fn invalid_csv(error: csv::Error) -> AppError {
+    AppError::bad_request(format!("Invalid CSV: {error}"))
+}
+
+/// Write every record of a listing matching the filters in `uri` as CSV.
+///
+/// Takes the same filters and `sort` as the list endpoint but ignores `limit` and
+/// `cursor`. References are written as names, so the file can be imported back.
+pub async fn export(pool: &DbPool, kind: Kind, uri: &Uri) -> Result<Vec<u8>, AppError> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    match kind {
+        Kind::Track => {
+            let filter: TrackFilter = filter(uri)?;
+            let tracks: Vec<Track> = fetch(pool, listing::tracks_query(&filter)?.all()).await?;
+            let albums = labels(pool, Kind::Album, tracks.iter().map(|t| t.album_id)).await?;
+            let album_artists = album_artists(pool, tracks.iter().map(|t| t.album_id)).await?;
+            let entregas = labels(pool, Kind::Entrega, tracks.iter().map(|t| t.entrega_id)).await?;
+
+            write(&mut writer, TRACK_EXPORT)?;
+            for track in tracks {
+                write(
+                    &mut writer,
+                    [
+                        track.id.to_string(),
+                        track.title,
+                        track.artist_name,
+                        label(&albums, track.album_id),
+                        label(&album_artists, track.album_id),
+                        label(&entregas, track.entrega_id),
+                        cell(track.position),
+                        cell(track.duration_seconds),
+                        cell(track.bpm),
+                        cell(track.tone),
+                        cell(track.score),
+                        track.created_at.to_rfc3339(),
+                        track.updated_at.to_rfc3339(),
+                    ],
+                )?;
+            }
+        }
+        Kind::Album => {
+            let filter: AlbumFilter = filter(uri)?;
+            let albums: Vec<Album> = fetch(pool, listing::albums_query(&filter)?.all()).await?;
+            let artists = labels(pool, Kind::Artist, albums.iter().map(|a| a.artist_id)).await?;
+
+            write(&mut writer, ALBUM_EXPORT)?;
+            for album in albums {
+                write(
+                    &mut writer,
+                    [
+                        album.id.to_string(),
+                        album.title,
+                        label(&artists, album.artist_id),
+                        cell(album.release_year),
+                        cell(album.label),
+                        cell(album.format),
+                        cell(album.country),
+                        cell(album.genre),
+                        cell(album.style),
+                        album.created_at.to_rfc3339(),
+                        album.updated_at.to_rfc3339(),
+                    ],
+                )?;
+            }
+        }
+        Kind::Artist => {
+            let filter: ArtistFilter = filter(uri)?;
+            let artists: Vec<Artist> = fetch(pool, listing::artists_query(&filter)?.all()).await?;
+
+            write(&mut writer, ARTIST_EXPORT)?;
+            for artist in artists {
+                write(
+                    &mut writer,
+                    [
+                        artist.id.to_string(),
+                        artist.name,
+                        cell(artist.country),
+                        artist.created_at.to_rfc3339(),
+                        artist.updated_at.to_rfc3339(),
+                    ],
+                )?;
+            }
+        }
+        Kind::Batch => {
+            let filter: BatchFilter = filter(uri)?;
+            let batches: Vec<Batch> = fetch(pool, listing::batches_query(&filter)?.all()).await?;
+
+            write(&mut writer, BATCH_EXPORT)?;
+            for batch in batches {
+                write(
+                    &mut writer,
+                    [
+                        batch.id.to_string(),
+                        batch.name,
+                        batch.created_at.to_rfc3339(),
+                        batch.updated_at.to_rfc3339(),
+                    ],
+                )?;
+            }
+        }
+        Kind::Entrega => {
+            let filter: EntregaFilter = filter(uri)?;
+            let entregas: Vec<Entrega> =
+                fetch(pool, listing::entregas_query(&filter)?.all()).await?;
+            let batches = labels(pool, Kind::Batch, entregas.iter().map(|e| e.batch_id)).await?;
+
+            write(&mut writer, ENTREGA_EXPORT)?;
+            for entrega in entregas {
+                write(
+                    &mut writer,
+                    [
+                        entrega.id.to_string(),
+                        entrega.name,
+                        label(&batches, entrega.batch_id),
+                        entrega.created_at.to_rfc3339(),
+                        entrega.updated_at.to_rfc3339(),
+                    ],
+                )?;
+            }
+        }
+    }
+
+    writer
+        .into_inner()
+        .map_err(|error| AppError::internal(format!("Could not write the CSV: {error}")))
+}
+
+fn filter<T: DeserializeOwned>(uri: &Uri) -> Result<T, AppError> {
+    let Query(filter) = Query::try_from_uri(uri)?;
+    Ok(filter)
+}
+
+async fn fetch<T>(
+    pool: &DbPool,
+    mut query: QueryBuilder<'static, Postgres>,
+) -> Result<Vec<T>, AppError>
+where
+    T: for<'r> FromRow<'r, sqlx::postgres::PgRow> + Send + Unpin,
+{
+    Ok(query.build_query_as::<T>().fetch_all(pool).await?)
+}
+
+/// Names of the referenced records, trashed ones included.
+pub async fn labels(
+    pool: &DbPool,
+    kind: Kind,
+    ids: impl Iterator<Item = Option<i64>>,
+) -> Result<HashMap<i64, String>, AppError> {
+    let mut ids: Vec<i64> = ids.flatten().collect();
+    ids.sort_unstable();
+    ids.dedup();
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let rows: Vec<(i64, String)> = sqlx::query_as(&format!(
+        "SELECT id, {} FROM {} WHERE id = ANY($1)",
+        kind.label_column(),
+        kind.table()
+    ))
+    .bind(ids)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().collect())
+}
+
+/// Names of the albums' artists, which tell apart albums sharing a title.
+async fn album_artists(
+    pool: &DbPool,
+    ids: impl Iterator<Item = Option<i64>>,
+) -> Result<HashMap<i64, String>, AppError> {
+    let mut ids: Vec<i64> = ids.flatten().collect();
+    ids.sort_unstable();
+    ids.dedup();
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let rows: Vec<(i64, String)> = sqlx::query_as(
+        "SELECT al.id, ar.name FROM albums al JOIN artists ar ON ar.id = al.artist_id WHERE al.id = ANY($1)",
+    )
+    .bind(ids)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().collect())
+}
+
+fn label(labels: &HashMap<i64, String>, id: Option<i64>) -> String {
+    id.and_then(|id| labels.get(&id).cloned())
+        .unwrap_or_default()
+}
+
+fn cell<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn write<I>(writer: &mut csv::Writer<Vec<u8>>, record: I) -> Result<(), AppError>
+where
+    I: IntoIterator,
+    I::Item: AsRef<[u8]>,
+{
+    writer
+        .write_record(record)
+        .map_err(|error| AppError::internal(format!("Could not write the CSV: {error}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracks(csv: &str, map: Option<&str>) -> Vec<ImportItem<NewTrack>> {
+        parse(Kind::Track, csv.as_bytes(), map)
+            .unwrap()
+            .tracks
+            .unwrap()
+    }
+
+    fn error(kind: Kind, csv: &str, map: Option<&str>) -> AppError {
+        parse(kind, csv.as_bytes(), map).unwrap_err()
+    }
+
+    #[test]
+    fn reads_a_spreadsheet_export() {
+        // Byte order mark, semicolons and decimal commas, as a Spanish locale saves it
+        let csv = "\u{feff}Track;Artist;Album;Key;Tempo;Duration;Pos\n\
+                   5 Mouths;Fred Fresh;Neighbors;Db;126,46;5:30;A1\n\
+                   Work;Mind Phase One;;;;1:02:03;\n";
+        let items = tracks(csv, None);
+        assert_eq!(items.len(), 2);
+
+        let first = &items[0];
+        assert_eq!(first.record.title, "5 Mouths");
+        assert_eq!(first.record.artist_name, "Fred Fresh");
+        assert_eq!(first.record.tone.as_deref(), Some("Db"));
+        assert_eq!(first.record.bpm, Some(126.46));
+        assert_eq!(first.record.duration_seconds, Some(330));
+        assert_eq!(first.record.position.as_deref(), Some("A1"));
+        assert_eq!(first.refs.album.as_deref(), Some("Neighbors"));
+
+        // Empty cells are left out rather than read as empty strings
+        let second = &items[1];
+        assert_eq!(second.refs.album, None);
+        assert_eq!(second.record.tone, None);
+        assert_eq!(second.record.duration_seconds, Some(3723));
+    }
+
+    #[test]
+    fn renames_headers_with_map() {
+        let csv = "Título\tArtista\tTono\nTeacher\tESP\tCm\n";
+        let items = tracks(csv, Some("Título:title, Artista:artist_name,Tono:key"));
+        assert_eq!(items[0].record.title, "Teacher");
+        assert_eq!(items[0].record.artist_name, "ESP");
+        assert_eq!(items[0].record.tone.as_deref(), Some("Cm"));
+    }
+
+    #[test]
+    fn skips_the_columns_only_an_export_has() {
+        let csv = "id,title,artist,updated_at\n7,Work,Mind Phase One,2026-03-01T00:00:00Z\n";
+        let items = tracks(csv, None);
+        assert_eq!(items[0].record.title, "Work");
+    }
+
+    #[test]
+    fn reads_the_album_artist_of_an_export() {
+        let csv = TRACK_EXPORT.join(",")
+            + "\n7,Work,Mind Phase One,Neighbors,Fred Fresh,Primera Entrega,A1,,,,,,\n";
+        let items = tracks(&csv, None);
+        assert_eq!(items[0].refs.album.as_deref(), Some("Neighbors"));
+        assert_eq!(items[0].refs.album_artist.as_deref(), Some("Fred Fresh"));
+        assert_eq!(items[0].refs.entrega.as_deref(), Some("Primera Entrega"));
+    }
+
+    #[test]
+    fn reads_albums_with_their_artist_by_name() {
+        let csv = "title,artist,year,label\nNeighbors,Fred Fresh,1995,Strictly Rhythm\n";
+        let payload = parse(Kind::Album, csv.as_bytes(), None).unwrap();
+        assert!(payload.tracks.is_none());
+
+        let albums = payload.albums.unwrap();
+        assert_eq!(albums[0].record.release_year, Some(1995));
+        assert_eq!(albums[0].record.label.as_deref(), Some("Strictly Rhythm"));
+        assert_eq!(albums[0].refs.artist.as_deref(), Some("Fred Fresh"));
+    }
+
+    #[test]
+    fn reports_cells_that_do_not_parse_by_row() {
+        let csv = "title,artist,bpm,duration\nA,B,120,3:00\nC,D,fast,3 min\n";
+        let errors = error(Kind::Track, csv, None).errors;
+        let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(fields, ["tracks[1].duration_seconds", "tracks[1].bpm"]);
+    }
+
+    #[test]
+    fn validates_the_rows_like_a_json_import() {
+        let csv = "title,artist,key\nWork,Mind Phase One,H\n";
+        let errors = error(Kind::Track, csv, None).errors;
+        assert_eq!(errors[0].field, "tracks[0].tone");
+    }
+
+    #[test]
+    fn rejects_unusable_headers() {
+        let unknown = error(Kind::Track, "title,artist,mood\nA,B,happy\n", None);
+        assert!(unknown.message.contains("Unknown column `mood`"));
+
+        let twice = error(Kind::Track, "title,track\nA,B\n", None);
+        assert!(twice.message.contains("appears twice"));
+
+        let untitled = error(Kind::Track, "artist\nB\n", None);
+        assert!(untitled.message.contains("title column"));
+
+        let bad_map = error(Kind::Track, "title\nA\n", Some("Mood:mood"));
+        assert_eq!(bad_map.field.as_deref(), Some("map"));
+
+        let wrong_kind = error(Kind::Artist, "name\nFred Fresh\n", None);
+        assert_eq!(wrong_kind.field.as_deref(), Some("type"));
+    }
+
+    #[test]
+    fn picks_the_delimiter_of_the_header_line() {
+        assert_eq!(delimiter("title,artist\n"), b',');
+        assert_eq!(delimiter("title;artist;bpm\n1,5;2,5\n"), b';');
+        assert_eq!(delimiter("title\tartist\n"), b'\t');
+        assert_eq!(delimiter("title\n"), b',');
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::OnceLock;
+
+use axum::response::Html;
+use minijinja::Environment;
+
+use crate::error::AppError;
+
+/// Where debug builds read templates from on every render, so edits show up on reload.
+const TEMPLATE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/templates");
+
+/// Every template, compiled into the binary for release builds.
+const EMBEDDED: &[(&str, &str)] = &[
+    ("layout.html", include_str!("../templates/layout.html")),
+    ("home.html", include_str!("../templates/home.html")),
+    ("login.html", include_str!("../templates/login.html")),
+    ("articles.html", include_str!("../templates/articles.html")),
+    ("article.html", include_str!("../templates/article.html")),
+    ("entrega.html", include_str!("../templates/entrega.html")),
+    ("album.html", include_str!("../templates/album.html")),
+    ("tracks.html", include_str!("../templates/tracks.html")),
+    (
+        "admin/layout.html",
+        include_str!("../templates/admin/layout.html"),
+    ),
+    (
+        "admin/dashboard.html",
+        include_str!("../templates/admin/dashboard.html"),
+    ),
+    (
+        "admin/list.html",
+        include_str!("../templates/admin/list.html"),
+    ),
+    (
+        "admin/form.html",
+        include_str!("../templates/admin/form.html"),
+    ),
+];
+
+fn environment() -> Environment<'static> {
+    let mut env = Environment::new();
+    if cfg!(debug_assertions) {
+        env.set_loader(minijinja::path_loader(TEMPLATE_DIR));
+    } else {
+        for (name, source) in EMBEDDED {
+            env.add_template(name, source)
+                .expect("the embedded templates are valid");
+        }
+    }
+    env
+}
+
+/// Render a page; `.html` templates escape their variables.
+pub fn render(name: &str, context: minijinja::Value) -> Result<Html<String>, AppError> {
+    static RELEASE: OnceLock<Environment<'static>> = OnceLock::new();
+    let fresh;
+    let env = if cfg!(debug_assertions) {
+        fresh = environment();
+        &fresh
+    } else {
+        RELEASE.get_or_init(environment)
+    };
+
+    env.get_template(name)
+        .and_then(|template| template.render(context))
+        .map(Html)
+        .map_err(|error| AppError::internal(format!("Could not render {name}: {error}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    /// Template names under `dir`, relative to [`TEMPLATE_DIR`].
+    fn template_files(dir: &Path, names: &mut Vec<String>) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                template_files(&path, names);
+            } else {
+                let name = path.strip_prefix(TEMPLATE_DIR).unwrap();
+                names.push(name.to_string_lossy().replace('\\', "/"));
+            }
+        }
+    }
+
+    #[test]
+    fn every_template_is_embedded() {
+        let mut files = Vec::new();
+        template_files(Path::new(TEMPLATE_DIR), &mut files);
+        files.sort();
+
+        let mut embedded: Vec<String> = EMBEDDED.iter().map(|(name, _)| name.to_string()).collect();
+        embedded.sort();
+
+        assert_eq!(
+            embedded, files,
+            "EMBEDDED must list every file in templates/"
+        );
+    }
+
+    #[test]
+    fn embedded_templates_compile() {
+        let mut env = Environment::new();
+        for (name, source) in EMBEDDED {
+            env.add_template(name, source)
+                .unwrap_or_else(|error| panic!("{name}: {error}"));
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgConnection, QueryBuilder};
+
+use crate::listing::{ColumnType, Listing, Order, SortColumn};
+
+/// Records the DELETE endpoints soft delete into the trash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Artist,
+    Album,
+    Track,
+    Batch,
+    Entrega,
+}
+
+pub const KINDS: [Kind; 5] = [
+    Kind::Artist,
+    Kind::Album,
+    Kind::Track,
+    Kind::Batch,
+    Kind::Entrega,
+];
+
+impl Kind {
+    pub fn parse(name: &str) -> Option<Self> {
+        KINDS.into_iter().find(|kind| kind.as_str() == name)
+    }
+
+    /// The kind stored in a table, e.g. `albums`.
+    pub fn from_table(table: &str) -> Option<Self> {
+        KINDS.into_iter().find(|kind| kind.table() == table)
+    }
+
+    /// Name used by `/trash?type=` and `/trash/{type}/{id}/restore`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Kind::Artist => "artist",
+            Kind::Album => "album",
+            Kind::Track => "track",
+            Kind::Batch => "batch",
+            Kind::Entrega => "entrega",
+        }
+    }
+
+    /// Capitalized name for error messages.
+    pub fn resource(self) -> &'static str {
+        match self {
+            Kind::Artist => "Artist",
+            Kind::Album => "Album",
+            Kind::Track => "Track",
+            Kind::Batch => "Batch",
+            Kind::Entrega => "Entrega",
+        }
+    }
+
+    pub fn table(self) -> &'static str {
+        match self {
+            Kind::Artist => "artists",
+            Kind::Album => "albums",
+            Kind::Track => "tracks",
+            Kind::Batch => "batches",
+            Kind::Entrega => "entregas",
+        }
+    }
+
+    /// Column that names a record in listings and dropdowns.
+    pub fn label_column(self) -> &'static str {
+        match self {
+            Kind::Album | Kind::Track => "title",
+            Kind::Artist | Kind::Batch | Kind::Entrega => "name",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct TrashItem {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub id: i64,
+    pub label: String,
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// Whether the record exists at all, trashed or not.
+pub async fn exists(conn: &mut PgConnection, kind: Kind, id: i64) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(&format!(
+        "SELECT EXISTS (SELECT 1 FROM {} WHERE id = $1)",
+        kind.table()
+    ))
+    .bind(id)
+    .fetch_one(conn)
+    .await
+}
+
+/// Move a live record to the trash. Returns `false` when it is missing or already trashed.
+pub async fn soft_delete(
+    conn: &mut PgConnection,
+    kind: Kind,
+    id: i64,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(&format!(
+        "UPDATE {} SET deleted_at = now(), updated_at = now() WHERE id = $1 AND deleted_at IS NULL",
+        kind.table()
+    ))
+    .bind(id)
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Take a record out of the trash. Returns `false` when it is not in the trash.
+pub async fn restore(conn: &mut PgConnection, kind: Kind, id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(&format!(
+        "UPDATE {} SET deleted_at = NULL, updated_at = now() WHERE id = $1 AND deleted_at IS NOT NULL",
+        kind.table()
+    ))
+    .bind(id)
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Rows that reference the record, trashed or not, as `(what, count)` pairs.
+/// A hard delete without cascade is refused while any remain.
+pub async fn dependents(
+    conn: &mut PgConnection,
+    kind: Kind,
+    id: i64,
+) -> Result<Vec<(&'static str, i64)>, sqlx::Error> {
+    let checks: &[(&str, &str)] = match kind {
+        Kind::Artist => &[
+            ("albums", "SELECT count(*) FROM albums WHERE artist_id = $1"),
+            (
+                "track credits",
+                "SELECT count(*) FROM track_artists WHERE artist_id = $1",
+            ),
+        ],
+        Kind::Album => &[
+            ("tracks", "SELECT count(*) FROM tracks WHERE album_id = $1"),
+            (
+                "live set entries",
+                "SELECT count(*) FROM live_set_entries WHERE album_id = $1",
+            ),
+        ],
+        Kind::Track => &[(
+            "live set entries",
+            "SELECT count(*) FROM live_set_entries WHERE track_id = $1",
+        )],
+        Kind::Batch => &[(
+            "entregas",
+            "SELECT count(*) FROM entregas WHERE batch_id = $1",
+        )],
+        Kind::Entrega => &[
+            (
+                "tracks",
+                "SELECT count(*) FROM tracks WHERE entrega_id = $1",
+            ),
+            (
+                "articles",
+                "SELECT count(*) FROM articles WHERE entrega_id = $1",
+            ),
+        ],
+    };
+
+    let mut found = Vec::new();
+    for (what, sql) in checks {
+        let count: i64 = sqlx::query_scalar(sql)
+            .bind(id)
+            .fetch_one(&mut *conn)
+            .await?;
+        if count > 0 {
+            found.push((*what, count));
+        }
+    }
+
+    Ok(found)
+}
+
+/// Permanently delete a record together with everything that depends on it.
+///
+/// Albums of an artist, entregas of a batch and the tracks of those albums and
+/// entregas go with it, as do live set entries pointing at removed tracks or albums.
+/// Articles are only detached, since their Markdown file is the source of truth.
+/// Returns the number of rows removed per table.
+pub async fn hard_delete(
+    conn: &mut PgConnection,
+    kind: Kind,
+    id: i64,
+) -> Result<BTreeMap<&'static str, u64>, sqlx::Error> {
+    let mut album_ids: Vec<i64> = Vec::new();
+    let mut entrega_ids: Vec<i64> = Vec::new();
+    let mut track_ids: Vec<i64> = Vec::new();
+    match kind {
+        Kind::Artist => {
+            album_ids = sqlx::query_scalar("SELECT id FROM albums WHERE artist_id = $1")
+                .bind(id)
+                .fetch_all(&mut *conn)
+                .await?;
+        }
+        Kind::Album => album_ids.push(id),
+        Kind::Track => track_ids.push(id),
+        Kind::Batch => {
+            entrega_ids = sqlx::query_scalar("SELECT id FROM entregas WHERE batch_id = $1")
+                .bind(id)
+                .fetch_all(&mut *conn)
+                .await?;
+        }
+        Kind::Entrega => entrega_ids.push(id),
+    }
+
+    let contained: Vec<i64> = sqlx::query_scalar(
+        "SELECT id FROM tracks WHERE album_id = ANY($1) OR entrega_id = ANY($2)",
+    )
+    .bind(&album_ids)
+    .bind(&entrega_ids)
+    .fetch_all(&mut *conn)
+    .await?;
+    track_ids.extend(contained);
+
+    let mut removed = BTreeMap::new();
+    let entries =
+        sqlx::query("DELETE FROM live_set_entries WHERE track_id = ANY($1) OR album_id = ANY($2)")
+            .bind(&track_ids)
+            .bind(&album_ids)
+            .execute(&mut *conn)
+            .await?;
+    removed.insert("live_set_entries", entries.rows_affected());
+
+    // Credits go with their tracks through ON DELETE CASCADE
+    let tracks = sqlx::query("DELETE FROM tracks WHERE id = ANY($1)")
+        .bind(&track_ids)
+        .execute(&mut *conn)
+        .await?;
+    removed.insert("tracks", tracks.rows_affected());
+
+    let articles = sqlx::query(
+        "UPDATE articles SET entrega_id = NULL, updated_at = now() WHERE entrega_id = ANY($1)",
+    )
+    .bind(&entrega_ids)
+    .execute(&mut *conn)
+    .await?;
+    removed.insert("articles_detached", articles.rows_affected());
+
+    let entregas = sqlx::query("DELETE FROM entregas WHERE id = ANY($1)")
+        .bind(&entrega_ids)
+        .execute(&mut *conn)
+        .await?;
+    removed.insert("entregas", entregas.rows_affected());
+
+    let albums = sqlx::query("DELETE FROM albums WHERE id = ANY($1)")
+        .bind(&album_ids)
+        .execute(&mut *conn)
+        .await?;
+    removed.insert("albums", albums.rows_affected());
+
+    if kind == Kind::Artist {
+        let credits = sqlx::query("DELETE FROM track_artists WHERE artist_id = $1")
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+        removed.insert("track_artists", credits.rows_affected());
+    }
+    // Albums, tracks and entregas were removed along with their dependents above
+    if matches!(kind, Kind::Artist | Kind::Batch) {
+        let root = sqlx::query(&format!("DELETE FROM {} WHERE id = $1", kind.table()))
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+        removed.insert(kind.table(), root.rows_affected());
+    }
+
+    removed.retain(|_, count| *count > 0);
+    Ok(removed)
+}
+
+/// Trashed records of the given kinds, most recently deleted first.
+pub fn trash_query(kinds: &[Kind]) -> Listing {
+    let selects: Vec<String> = KINDS
+        .iter()
+        .map(|kind| {
+            format!(
+                "SELECT '{}' AS kind, id, {} AS label, deleted_at FROM {} WHERE deleted_at IS NOT NULL",
+                kind.as_str(),
+                kind.label_column(),
+                kind.table()
+            )
+        })
+        .collect();
+
+    let mut query = QueryBuilder::new(format!(
+        "SELECT kind, id, label, deleted_at FROM ({}) trash WHERE kind = ANY(",
+        selects.join(" UNION ALL ")
+    ));
+    query
+        .push_bind(kinds.iter().map(|kind| kind.as_str()).collect::<Vec<_>>())
+        .push(")");
+    let order = Order::new(vec![
+        SortColumn::new("deleted_at", ColumnType::Timestamp).descending(),
+        SortColumn::new("kind", ColumnType::Text).field("type"),
+        SortColumn::new("id", ColumnType::Integer),
+    ]);
+
+    Listing::new(query, order)
+}
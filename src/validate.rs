@@ -0,0 +1,508 @@
+use chrono::{Datelike, Utc};
+use serde::Serialize;
+
+use crate::auth::Role;
+use crate::keys::TONES;
+use crate::models::{
+    AlbumPatch, ArtistPatch, BatchPatch, EntregaPatch, ImportItem, ImportPayload,
+    LiveSetEntryPatch, LiveSetPatch, NewAlbum, NewApiToken, NewArtist, NewBatch, NewEntrega,
+    NewLiveSet, NewLiveSetEntry, NewTrack, NewUser, TrackPatch, UserPatch,
+};
+
+/// Accepted tempo range, wide enough for half-time ambient and double-time breakcore.
+pub const BPM_RANGE: std::ops::RangeInclusive<f64> = 20.0..=300.0;
+/// Earliest accepted release year (first commercial recordings).
+pub const MIN_RELEASE_YEAR: i64 = 1888;
+pub const MIN_PASSWORD_LENGTH: usize = 10;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    /// Qualify the field with its place in a payload, e.g. `tracks[3].bpm`.
+    pub fn within(self, prefix: &str) -> Self {
+        Self {
+            field: format!("{prefix}.{}", self.field),
+            message: self.message,
+        }
+    }
+}
+
+impl std::fmt::Display for FieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// Checks run on incoming records before they reach the database.
+pub trait Validate {
+    fn validate(&self) -> Result<(), Vec<FieldError>>;
+}
+
+/// Collects every failing field instead of stopping at the first one.
+#[derive(Default)]
+struct Checks(Vec<FieldError>);
+
+impl Checks {
+    fn check(&mut self, field: &str, result: Result<(), String>) -> &mut Self {
+        if let Err(message) = result {
+            self.0.push(FieldError {
+                field: field.to_string(),
+                message,
+            });
+        }
+        self
+    }
+
+    fn check_opt<T>(
+        &mut self,
+        field: &str,
+        value: Option<T>,
+        rule: impl FnOnce(T) -> Result<(), String>,
+    ) -> &mut Self {
+        match value {
+            Some(value) => self.check(field, rule(value)),
+            None => self,
+        }
+    }
+
+    fn finish(&mut self) -> Result<(), Vec<FieldError>> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(std::mem::take(&mut self.0))
+        }
+    }
+}
+
+impl Validate for NewArtist {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Checks::default()
+            .check("name", not_blank(&self.name))
+            .finish()
+    }
+}
+
+impl Validate for NewAlbum {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Checks::default()
+            .check("title", not_blank(&self.title))
+            .check_opt("artist_id", self.artist_id, positive_id)
+            .check_opt("release_year", self.release_year, release_year)
+            .finish()
+    }
+}
+
+impl Validate for NewTrack {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Checks::default()
+            .check("title", not_blank(&self.title))
+            .check("artist_name", not_blank(&self.artist_name))
+            .check_opt("album_id", self.album_id, positive_id)
+            .check_opt("duration_seconds", self.duration_seconds, duration)
+            .check_opt("bpm", self.bpm, bpm)
+            .check_opt("tone", self.tone.as_deref(), tone)
+            .check_opt("position", self.position.as_deref(), position)
+            .check_opt("entrega_id", self.entrega_id, positive_id)
+            .finish()
+    }
+}
+
+impl Validate for NewBatch {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Checks::default()
+            .check("name", not_blank(&self.name))
+            .finish()
+    }
+}
+
+impl Validate for NewEntrega {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Checks::default()
+            .check("name", not_blank(&self.name))
+            .check_opt("batch_id", self.batch_id, positive_id)
+            .finish()
+    }
+}
+
+impl Validate for NewLiveSet {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Checks::default()
+            .check("name", not_blank(&self.name))
+            .finish()
+    }
+}
+
+impl Validate for NewLiveSetEntry {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Checks::default()
+            .check_opt("track_id", self.track_id, positive_id)
+            .check_opt("album_id", self.album_id, positive_id)
+            .check("offset_seconds", offset(self.offset_seconds))
+            .finish()
+    }
+}
+
+// PATCH bodies only check the keys they carry; `null` on a nullable column is always fine.
+
+impl Validate for ArtistPatch {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Checks::default()
+            .check_opt("name", self.name.as_deref(), not_blank)
+            .finish()
+    }
+}
+
+impl Validate for AlbumPatch {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Checks::default()
+            .check_opt("title", self.title.as_deref(), not_blank)
+            .check_opt("artist_id", self.artist_id.flatten(), positive_id)
+            .check_opt("release_year", self.release_year.flatten(), release_year)
+            .finish()
+    }
+}
+
+impl Validate for TrackPatch {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Checks::default()
+            .check_opt("title", self.title.as_deref(), not_blank)
+            .check_opt("artist_name", self.artist_name.as_deref(), not_blank)
+            .check_opt("album_id", self.album_id.flatten(), positive_id)
+            .check_opt(
+                "duration_seconds",
+                self.duration_seconds.flatten(),
+                duration,
+            )
+            .check_opt("bpm", self.bpm.flatten(), bpm)
+            .check_opt("tone", self.tone.as_ref().and_then(Option::as_deref), tone)
+            .check_opt(
+                "position",
+                self.position.as_ref().and_then(Option::as_deref),
+                position,
+            )
+            .check_opt("entrega_id", self.entrega_id.flatten(), positive_id)
+            .finish()
+    }
+}
+
+impl Validate for BatchPatch {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Checks::default()
+            .check_opt("name", self.name.as_deref(), not_blank)
+            .finish()
+    }
+}
+
+impl Validate for EntregaPatch {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Checks::default()
+            .check_opt("name", self.name.as_deref(), not_blank)
+            .check_opt("batch_id", self.batch_id.flatten(), positive_id)
+            .finish()
+    }
+}
+
+impl Validate for LiveSetPatch {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Checks::default()
+            .check_opt("name", self.name.as_deref(), not_blank)
+            .finish()
+    }
+}
+
+impl Validate for LiveSetEntryPatch {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Checks::default()
+            .check_opt("track_id", self.track_id.flatten(), positive_id)
+            .check_opt("album_id", self.album_id.flatten(), positive_id)
+            .check_opt("offset_seconds", self.offset_seconds, offset)
+            .finish()
+    }
+}
+
+impl Validate for NewUser {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Checks::default()
+            .check("username", not_blank(&self.username))
+            .check("password", password(&self.password))
+            .check_opt("role", self.role.as_deref(), role)
+            .finish()
+    }
+}
+
+impl Validate for UserPatch {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Checks::default()
+            .check_opt("password", self.password.as_deref(), password)
+            .check_opt("role", self.role.as_deref(), role)
+            .finish()
+    }
+}
+
+impl Validate for NewApiToken {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Checks::default()
+            .check("name", not_blank(&self.name))
+            .finish()
+    }
+}
+
+impl<T: Validate> Validate for ImportItem<T> {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        self.record.validate()
+    }
+}
+
+impl Validate for ImportPayload {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = validate_all("albums", self.albums.as_deref());
+        errors.extend(validate_all("tracks", self.tracks.as_deref()));
+        errors.extend(validate_all("batches", self.batches.as_deref()));
+        errors.extend(validate_all("entregas", self.entregas.as_deref()));
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Validate every item of a list, prefixing fields with `{name}[index]`.
+fn validate_all<T: Validate>(name: &str, items: Option<&[T]>) -> Vec<FieldError> {
+    items
+        .unwrap_or_default()
+        .iter()
+        .enumerate()
+        .filter_map(|(index, item)| item.validate().err().map(|errors| (index, errors)))
+        .flat_map(|(index, errors)| {
+            let prefix = format!("{name}[{index}]");
+            errors.into_iter().map(move |error| error.within(&prefix))
+        })
+        .collect()
+}
+
+fn not_blank(value: &str) -> Result<(), String> {
+    if value.trim().is_empty() {
+        Err("must not be blank".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+fn positive_id(id: i64) -> Result<(), String> {
+    if id > 0 {
+        Ok(())
+    } else {
+        Err("must be a positive id".to_string())
+    }
+}
+
+fn bpm(bpm: f64) -> Result<(), String> {
+    if BPM_RANGE.contains(&bpm) {
+        Ok(())
+    } else {
+        Err(format!(
+            "must be between {} and {}",
+            BPM_RANGE.start(),
+            BPM_RANGE.end()
+        ))
+    }
+}
+
+fn duration(seconds: i64) -> Result<(), String> {
+    if seconds > 0 {
+        Ok(())
+    } else {
+        Err("must be a positive number of seconds".to_string())
+    }
+}
+
+/// Seconds into a live set.
+fn offset(seconds: i64) -> Result<(), String> {
+    if seconds >= 0 {
+        Ok(())
+    } else {
+        Err("must not be negative".to_string())
+    }
+}
+
+fn tone(tone: &str) -> Result<(), String> {
+    if TONES.contains(&tone) {
+        Ok(())
+    } else {
+        Err(format!(
+            "`{tone}` is not a known tone (expected e.g. C, F#, Bbm)"
+        ))
+    }
+}
+
+fn password(password: &str) -> Result<(), String> {
+    if password.chars().count() >= MIN_PASSWORD_LENGTH {
+        Ok(())
+    } else {
+        Err(format!("must be at least {MIN_PASSWORD_LENGTH} characters"))
+    }
+}
+
+fn role(role: &str) -> Result<(), String> {
+    if Role::parse(role).is_some() {
+        Ok(())
+    } else {
+        Err(format!(
+            "`{role}` is not a role (expected reader, editor or admin)"
+        ))
+    }
+}
+
+fn release_year(year: i64) -> Result<(), String> {
+    // Allow announced releases dated next year
+    let latest = i64::from(Utc::now().year()) + 1;
+    if (MIN_RELEASE_YEAR..=latest).contains(&year) {
+        Ok(())
+    } else {
+        Err(format!("must be between {MIN_RELEASE_YEAR} and {latest}"))
+    }
+}
+
+/// Vinyl side and track (`A1`, `B2`, `C3b`), a side alone (`A`, `AA` for double A-sides)
+/// or a plain CD/digital track number (`7`, `12`).
+fn position(position: &str) -> Result<(), String> {
+    let side_len = position
+        .chars()
+        .take_while(char::is_ascii_uppercase)
+        .count();
+    let (side, rest) = position.split_at(side_len);
+    let digits_len = rest.chars().take_while(char::is_ascii_digit).count();
+    let suffix = &rest[digits_len..];
+
+    let valid = match (side_len, digits_len) {
+        (0, 0) => false,
+        // Plain track number
+        (0, 1..=3) => suffix.is_empty(),
+        // A side letter, doubled at most, optionally numbered with a sub-index letter
+        (1..=2, _) => {
+            side.chars().all(|c| side.starts_with(c))
+                && digits_len <= 2
+                && (suffix.is_empty() || (digits_len > 0 && is_sub_index(suffix)))
+        }
+        _ => false,
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "`{position}` is not a record position like A1, B2, AA or 7"
+        ))
+    }
+}
+
+fn is_sub_index(suffix: &str) -> bool {
+    let mut chars = suffix.chars();
+    matches!((chars.next(), chars.next()), (Some(c), None) if c.is_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn fields(errors: Vec<FieldError>) -> Vec<String> {
+        errors.into_iter().map(|error| error.field).collect()
+    }
+
+    #[test]
+    fn accepts_record_positions() {
+        for valid in ["A1", "B2", "C3b", "A12", "A", "AA", "AA1", "7", "12", "123"] {
+            assert_eq!(position(valid), Ok(()), "{valid}");
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_positions() {
+        for invalid in [
+            "", "1234", "a1", "AB1", "AAA", "A123", "A1bb", "A1B", "Ab", "7b", "A-1", " A1",
+        ] {
+            assert!(position(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn reports_every_failing_field() {
+        let track: NewTrack = serde_json::from_value(json!({
+            "title": " ",
+            "artist_name": "Fred Fresh",
+            "bpm": 5.0,
+            "tone": "H",
+            "position": "A1"
+        }))
+        .unwrap();
+        assert_eq!(
+            fields(track.validate().unwrap_err()),
+            ["title", "bpm", "tone"]
+        );
+    }
+
+    #[test]
+    fn patches_only_check_the_keys_they_carry() {
+        let patch: LiveSetEntryPatch = serde_json::from_value(json!({ "note": null })).unwrap();
+        assert!(patch.validate().is_ok());
+
+        let patch: LiveSetEntryPatch =
+            serde_json::from_value(json!({ "offset_seconds": -1 })).unwrap();
+        assert_eq!(fields(patch.validate().unwrap_err()), ["offset_seconds"]);
+    }
+
+    #[test]
+    fn live_set_entries_share_the_offset_rule() {
+        let entry: NewLiveSetEntry =
+            serde_json::from_value(json!({ "offset_seconds": -30, "track_id": 0 })).unwrap();
+        assert_eq!(
+            fields(entry.validate().unwrap_err()),
+            ["track_id", "offset_seconds"]
+        );
+
+        let set: NewLiveSet = serde_json::from_value(json!({ "name": "" })).unwrap();
+        assert_eq!(fields(set.validate().unwrap_err()), ["name"]);
+    }
+
+    #[test]
+    fn live_sets_air_on_a_date_or_timestamp() {
+        let aired_at = |value: &str| {
+            let set: NewLiveSet =
+                serde_json::from_value(json!({ "name": "Vivo", "aired_at": value })).unwrap();
+            set.aired_at_utc().map(|at| at.unwrap().to_rfc3339())
+        };
+        assert_eq!(aired_at("2026-3-5").unwrap(), "2026-03-05T00:00:00+00:00");
+        assert_eq!(
+            aired_at("2026-03-12T21:00:00+01:00").unwrap(),
+            "2026-03-12T20:00:00+00:00"
+        );
+        assert_eq!(
+            aired_at("5/3/2026").unwrap_err().field.as_deref(),
+            Some("aired_at")
+        );
+
+        let patch: LiveSetPatch = serde_json::from_value(json!({ "aired_at": null })).unwrap();
+        assert_eq!(patch.aired_at_utc().unwrap(), Some(None));
+    }
+
+    #[test]
+    fn import_errors_name_their_place_in_the_payload() {
+        let payload: ImportPayload = serde_json::from_value(json!({
+            "albums": [{ "title": "Neighbors" }, { "title": "Later", "release_year": 1700 }],
+            "tracks": [{ "title": "Work", "artist_name": "", "entrega": "Primera Entrega" }]
+        }))
+        .unwrap();
+        assert_eq!(
+            fields(payload.validate().unwrap_err()),
+            ["albums[1].release_year", "tracks[0].artist_name"]
+        );
+    }
+}